@@ -0,0 +1,104 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::prelude::*;
+use bevy_rainbow::{make_mesh, make_tail_indices, make_tail_mesh, Player};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+// only the bench binary counts allocations, the game keeps the system allocator
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const TAIL_LENGTHS: [usize; 3] = [32, 128, 512];
+const ALLOCATION_ROUNDS: usize = 1000;
+
+// criterion only times, allocations per call are counted over a separate run
+fn report_allocations(name: &str, len: usize, mut f: impl FnMut(usize)) {
+    // one warm up round so lazily grown buffers don't count
+    f(0);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    for i in 0..ALLOCATION_ROUNDS {
+        f(i);
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{}/{}: {:.1} allocations per call",
+        name,
+        len,
+        allocations as f32 / ALLOCATION_ROUNDS as f32
+    );
+}
+
+fn push_tail_node(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_tail_node");
+    for &len in &TAIL_LENGTHS {
+        let mut player = Player::full(len);
+        let mut push = move |i: usize| {
+            player.push_tail_node(Vec2::new(i as f32, (i as f32 * 0.1).cos() * 50.));
+        };
+        report_allocations("push_tail_node", len, &mut push);
+        group.bench_function(BenchmarkId::from_parameter(len), |b| {
+            let mut i = 0;
+            b.iter(|| {
+                i += 1;
+                push(black_box(i))
+            })
+        });
+    }
+    group.finish();
+}
+
+fn tail_indices(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_tail_indices");
+    for &len in &TAIL_LENGTHS {
+        report_allocations("make_tail_indices", len, |_| {
+            make_tail_indices(len, &[]);
+        });
+        group.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| make_tail_indices(black_box(len), &[]))
+        });
+    }
+    group.finish();
+}
+
+fn tail_mesh(c: &mut Criterion) {
+    let mut group = c.benchmark_group("make_tail_mesh");
+    for &len in &TAIL_LENGTHS {
+        let player = Player::full(len);
+        let mut mesh = make_mesh(&[], vec![]);
+        report_allocations("make_tail_mesh", len, |_| {
+            make_tail_mesh(&mut mesh, &player.tail)
+        });
+        group.bench_function(BenchmarkId::from_parameter(len), |b| {
+            b.iter(|| make_tail_mesh(&mut mesh, black_box(&player.tail)))
+        });
+    }
+    group.finish();
+}
+
+// no window or gpu, only cpu side Mesh values
+criterion_group!(benches, push_tail_node, tail_indices, tail_mesh);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{CrossedTailEvent, TailNodePushed};
+
+// asset paths of the sounds, mp3 with the default features; None stays silent
+pub struct TailAudioConfig {
+    pub node_pushed: Option<String>,
+    pub crossed: Option<String>,
+    // seconds before the same sound may play again, nodes come in every tail tick
+    pub min_interval: f32,
+}
+
+impl Default for TailAudioConfig {
+    fn default() -> Self {
+        Self {
+            node_pushed: None,
+            crossed: None,
+            min_interval: 0.1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TailAudioState {
+    node_pushed_reader: EventReader<TailNodePushed>,
+    crossed_reader: EventReader<CrossedTailEvent>,
+    // seconds_since_startup of the last play of each sound
+    node_pushed_at: Option<f64>,
+    crossed_at: Option<f64>,
+}
+
+fn play_throttled(
+    audio: &Audio,
+    asset_server: &AssetServer,
+    path: Option<&str>,
+    last: &mut Option<f64>,
+    now: f64,
+    min_interval: f32,
+) {
+    let path = match path {
+        Some(path) => path,
+        None => return,
+    };
+    if last.is_some_and(|last| now - last < min_interval as f64) {
+        return;
+    }
+    *last = Some(now);
+    // the asset server hands back the same handle for a path it already loaded
+    audio.play(asset_server.load(path));
+}
+
+pub fn tail_audio_system(
+    time: Res<Time>,
+    config: Res<TailAudioConfig>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    mut state: Local<TailAudioState>,
+    node_pushed_events: Res<Events<TailNodePushed>>,
+    crossed_events: Res<Events<CrossedTailEvent>>,
+) {
+    let now = time.seconds_since_startup();
+    let state = &mut *state;
+    // readers are drained even while silent, so nothing old plays once a sound is set
+    if state.node_pushed_reader.iter(&node_pushed_events).count() > 0 {
+        play_throttled(
+            &audio,
+            &asset_server,
+            config.node_pushed.as_deref(),
+            &mut state.node_pushed_at,
+            now,
+            config.min_interval,
+        );
+    }
+    if state.crossed_reader.iter(&crossed_events).count() > 0 {
+        play_throttled(
+            &audio,
+            &asset_server,
+            config.crossed.as_deref(),
+            &mut state.crossed_at,
+            now,
+            config.min_interval,
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::borrow::Cow;
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::ActiveCameras,
+        mesh::shape,
+        pass::{
+            LoadOp, Operations, PassDescriptor, RenderPassColorAttachmentDescriptor,
+            RenderPassDepthStencilAttachmentDescriptor, TextureAttachment,
+        },
+        pipeline::{PipelineDescriptor, RenderPipeline},
+        render_graph::{
+            base::MainPass, AssetRenderResourcesNode, CameraNode, Node, PassNode, RenderGraph,
+            ResourceSlotInfo, ResourceSlots,
+        },
+        renderer::{
+            RenderContext, RenderResourceContext, RenderResourceId, RenderResourceType,
+            RenderResources, TextureId,
+        },
+        shader::{ShaderStage, ShaderStages},
+        texture::{
+            Extent3d, SamplerDescriptor, TextureDescriptor, TextureFormat, TextureUsage,
+            SAMPLER_ASSET_INDEX, TEXTURE_ASSET_INDEX,
+        },
+    },
+};
+
+use crate::capture::{TailCapture, CAPTURE_PASS, CAPTURE_TEXTURE_HANDLE};
+
+const BLUR_TEXTURE_NODE: &str = "blur_textures";
+const BLUR_MATERIAL_NODE: &str = "blur_material";
+// most texels sampled on either side, the shader loop is unrolled up to this
+const MAX_BLUR_RADIUS: f32 = 32.;
+
+const BLUR_H_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x1b9e_64d2_7c05_a3f1);
+// the capture blurred both ways, drawn over the scene when blur is on
+pub const BLUR_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x8d21_f0a7_35c6_4e9b);
+
+const BLUR_VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec2 Vertex_Uv;
+layout(location = 0) out vec2 v_uv;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+    v_uv = Vertex_Uv;
+}
+"#;
+
+const BLUR_FRAGMENT_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec2 v_uv;
+layout(location = 0) out vec4 o_Target;
+layout(set = 2, binding = 0) uniform BlurMaterial_direction {
+    vec2 direction;
+};
+layout(set = 2, binding = 1) uniform BlurMaterial_radius {
+    float radius;
+};
+layout(set = 2, binding = 2) uniform texture2D BlurMaterial_texture;
+layout(set = 2, binding = 3) uniform sampler BlurMaterial_texture_sampler;
+
+// one gaussian pass along direction, which is a single texel step
+void main() {
+    float sigma = max(radius / 2.0, 0.5);
+    vec4 sum = vec4(0.0);
+    float weights = 0.0;
+    for (int i = -32; i <= 32; i++) {
+        float offset = float(i);
+        if (abs(offset) > radius) {
+            continue;
+        }
+        float weight = exp(-0.5 * (offset * offset) / (sigma * sigma));
+        sum += texture(
+            sampler2D(BlurMaterial_texture, BlurMaterial_texture_sampler),
+            v_uv + direction * offset
+        ) * weight;
+        weights += weight;
+    }
+    o_Target = sum / weights;
+}
+"#;
+
+// 0 leaves the capture untouched and skips the passes entirely
+pub struct TailBlur {
+    pub radius: f32,
+}
+
+#[derive(RenderResources, TypeUuid)]
+#[uuid = "6a0c3e55-02d4-4b7f-9f41-b1e2d7c8a930"]
+pub struct BlurMaterial {
+    direction: Vec2,
+    radius: f32,
+    texture: Handle<Texture>,
+}
+
+pub struct BlurPassH;
+pub struct BlurPassV;
+
+struct BlurTextureNode {
+    horizontal: TextureId,
+    vertical: TextureId,
+    depth: TextureId,
+}
+
+impl Node for BlurTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[
+            ResourceSlotInfo {
+                name: Cow::Borrowed("horizontal"),
+                resource_type: RenderResourceType::Texture,
+            },
+            ResourceSlotInfo {
+                name: Cow::Borrowed("vertical"),
+                resource_type: RenderResourceType::Texture,
+            },
+            ResourceSlotInfo {
+                name: Cow::Borrowed("depth"),
+                resource_type: RenderResourceType::Texture,
+            },
+        ];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        _resources: &Resources,
+        _render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        output.set(0, RenderResourceId::Texture(self.horizontal));
+        output.set(1, RenderResourceId::Texture(self.vertical));
+        output.set(2, RenderResourceId::Texture(self.depth));
+    }
+}
+
+fn blur_pass<Q: bevy::ecs::WorldQuery>(camera: &str, samples: u32) -> PassNode<Q> {
+    let mut pass = PassNode::<Q>::new(PassDescriptor {
+        color_attachments: vec![RenderPassColorAttachmentDescriptor {
+            attachment: TextureAttachment::Input("color_attachment".to_string()),
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color::NONE),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: samples,
+    });
+    pass.add_camera(camera);
+    pass
+}
+
+pub fn setup_blur(
+    commands: &mut Commands,
+    capture: Res<TailCapture>,
+    blur: Res<TailBlur>,
+    msaa: Res<Msaa>,
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    mut render_graph: ResMut<RenderGraph>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut blur_materials: ResMut<Assets<BlurMaterial>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !capture.enabled || blur.radius <= 0. {
+        return;
+    }
+    let size = Vec2::new(capture.width as f32, capture.height as f32);
+    let descriptor = TextureDescriptor {
+        size: Extent3d::new(capture.width, capture.height, 1),
+        sample_count: msaa.samples,
+        format: TextureFormat::default(),
+        usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+        ..Default::default()
+    };
+    let horizontal = render_resource_context.create_texture(descriptor);
+    let vertical = render_resource_context.create_texture(descriptor);
+    let depth = render_resource_context.create_texture(TextureDescriptor {
+        format: TextureFormat::Depth32Float,
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+        ..descriptor
+    });
+    let sampler = render_resource_context.create_sampler(&SamplerDescriptor::default());
+    for (handle, texture) in &[
+        (BLUR_H_TEXTURE_HANDLE, horizontal),
+        (BLUR_TEXTURE_HANDLE, vertical),
+    ] {
+        let handle: Handle<Texture> = handle.clone().typed();
+        render_resource_context.set_asset_resource(
+            &handle,
+            RenderResourceId::Texture(*texture),
+            TEXTURE_ASSET_INDEX,
+        );
+        render_resource_context.set_asset_resource(
+            &handle,
+            RenderResourceId::Sampler(sampler),
+            SAMPLER_ASSET_INDEX,
+        );
+    }
+
+    render_graph.add_system_node(
+        BLUR_MATERIAL_NODE,
+        AssetRenderResourcesNode::<BlurMaterial>::new(true),
+    );
+    render_graph.add_node(
+        BLUR_TEXTURE_NODE,
+        BlurTextureNode {
+            horizontal,
+            vertical,
+            depth,
+        },
+    );
+    render_graph.add_node(
+        "blur_h_pass",
+        blur_pass::<&BlurPassH>("blur_h_camera", msaa.samples),
+    );
+    render_graph.add_node(
+        "blur_v_pass",
+        blur_pass::<&BlurPassV>("blur_v_camera", msaa.samples),
+    );
+    for (pass, output, camera) in &[
+        ("blur_h_pass", "horizontal", "blur_h_camera"),
+        ("blur_v_pass", "vertical", "blur_v_camera"),
+    ] {
+        render_graph.add_system_node(*camera, CameraNode::new(*camera));
+        active_cameras.add(camera);
+        render_graph
+            .add_slot_edge(BLUR_TEXTURE_NODE, *output, *pass, "color_attachment")
+            .unwrap();
+        render_graph
+            .add_slot_edge(BLUR_TEXTURE_NODE, "depth", *pass, "depth")
+            .unwrap();
+        render_graph.add_node_edge(*camera, *pass).unwrap();
+        render_graph
+            .add_node_edge(BLUR_MATERIAL_NODE, *pass)
+            .unwrap();
+    }
+    render_graph
+        .add_node_edge(CAPTURE_PASS, "blur_h_pass")
+        .unwrap();
+    render_graph
+        .add_node_edge("blur_h_pass", "blur_v_pass")
+        .unwrap();
+
+    let pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, BLUR_VERTEX_SHADER)),
+        fragment: Some(shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            BLUR_FRAGMENT_SHADER,
+        ))),
+    }));
+    let quad = meshes.add(Mesh::from(shape::Quad::new(size)));
+    let radius = blur.radius.min(MAX_BLUR_RADIUS);
+    let passes = [
+        (
+            "blur_h_camera",
+            Vec2::new(1. / size.x, 0.),
+            CAPTURE_TEXTURE_HANDLE.typed(),
+        ),
+        (
+            "blur_v_camera",
+            Vec2::new(0., 1. / size.y),
+            BLUR_H_TEXTURE_HANDLE.typed(),
+        ),
+    ];
+    for (i, (camera_name, direction, texture)) in passes.iter().enumerate() {
+        let mut camera = Camera2dBundle::default();
+        camera.camera.name = Some(camera_name.to_string());
+        commands.spawn(camera);
+        // only the blur cameras should see the quads, so they leave the main pass
+        commands
+            .spawn(MeshBundle {
+                mesh: quad.clone(),
+                render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                    pipeline.clone(),
+                )]),
+                ..Default::default()
+            })
+            .with(blur_materials.add(BlurMaterial {
+                direction: *direction,
+                radius,
+                texture: texture.clone(),
+            }));
+        let quad_entity = commands.current_entity().unwrap();
+        commands.remove_one::<MainPass>(quad_entity);
+        if i == 0 {
+            commands.insert_one(quad_entity, BlurPassH);
+        } else {
+            commands.insert_one(quad_entity, BlurPassV);
+        }
+    }
+
+    // composited over the scene, the sharp ribbon stays underneath
+    commands.spawn(SpriteBundle {
+        material: color_materials.add(ColorMaterial {
+            color: Color::WHITE,
+            texture: Some(BLUR_TEXTURE_HANDLE.typed()),
+        }),
+        sprite: Sprite {
+            size,
+            resize_mode: SpriteResizeMode::Manual,
+        },
+        transform: Transform::from_translation(Vec3::new(0., 0., 0.5)),
+        ..Default::default()
+    });
+}
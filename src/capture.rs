@@ -0,0 +1,471 @@
+use std::{borrow::Cow, sync::Arc};
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::ActiveCameras,
+        pass::{
+            LoadOp, Operations, PassDescriptor, RenderPassColorAttachmentDescriptor,
+            RenderPassDepthStencilAttachmentDescriptor, TextureAttachment,
+        },
+        render_graph::{
+            base, CameraNode, Node, PassNode, RenderGraph, ResourceSlotInfo, ResourceSlots,
+        },
+        renderer::{
+            BufferId, BufferInfo, BufferUsage, RenderContext, RenderResourceContext,
+            RenderResourceId, RenderResourceType, TextureId,
+        },
+        texture::{
+            Extent3d, SamplerDescriptor, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsage, SAMPLER_ASSET_INDEX, TEXTURE_ASSET_INDEX,
+        },
+    },
+    wgpu::renderer::{WgpuRenderContext, WgpuRenderResourceContext},
+};
+use futures_lite::future;
+
+use crate::{Player, Tail};
+
+pub const CAPTURE_CAMERA: &str = "capture_camera";
+const CAPTURE_TEXTURE_NODE: &str = "capture_texture";
+pub const CAPTURE_PASS: &str = "capture_pass";
+const CAPTURE_READBACK_NODE: &str = "capture_readback";
+// wgpu wants every row of a texture to buffer copy to start on this many bytes
+const READBACK_ROW_ALIGNMENT: u32 = 256;
+const PREVIEW_SCALE: f32 = 0.25;
+
+// the captured frame, bind it to any material to show or post-process it
+pub const CAPTURE_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x4f7b_2c1e_9a36_d805);
+
+pub struct TailCapture {
+    pub enabled: bool,
+    pub width: u32,
+    pub height: u32,
+    // small quad in the corner showing the last captured frame
+    pub preview: bool,
+}
+
+impl Default for TailCapture {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            width: 1280,
+            height: 720,
+            preview: false,
+        }
+    }
+}
+
+// players and tails get this so the capture pass draws them
+#[derive(Default)]
+pub struct CapturePass;
+
+// a captured frame read back from the gpu, sent the frame after it was requested
+pub struct CaptureFrame(pub Arc<image::RgbaImage>);
+
+// how the screenshot and the gif get frames out of the capture texture: request,
+// the readback node copies the next capture into the buffer, and
+// capture_readback_system maps it and sends a CaptureFrame
+#[derive(Default)]
+pub struct CaptureReadback {
+    requested: bool,
+    copied: bool,
+    // made in setup_capture, none while capture is off
+    buffer: Option<BufferId>,
+    width: u32,
+    height: u32,
+}
+
+impl CaptureReadback {
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    fn bytes_per_row(&self) -> u32 {
+        (self.width * 4).div_ceil(READBACK_ROW_ALIGNMENT) * READBACK_ROW_ALIGNMENT
+    }
+}
+
+// hands the textures made in setup_capture to the pass, they outlive the graph
+struct CaptureTextureNode {
+    color: TextureId,
+    depth: TextureId,
+    // the single sampled texture msaa resolves into, the color texture without msaa
+    resolve: TextureId,
+}
+
+impl Node for CaptureTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[
+            ResourceSlotInfo {
+                name: Cow::Borrowed("color"),
+                resource_type: RenderResourceType::Texture,
+            },
+            ResourceSlotInfo {
+                name: Cow::Borrowed("depth"),
+                resource_type: RenderResourceType::Texture,
+            },
+            ResourceSlotInfo {
+                name: Cow::Borrowed("resolve"),
+                resource_type: RenderResourceType::Texture,
+            },
+        ];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        _resources: &Resources,
+        _render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        output.set(0, RenderResourceId::Texture(self.color));
+        output.set(1, RenderResourceId::Texture(self.depth));
+        output.set(2, RenderResourceId::Texture(self.resolve));
+    }
+}
+
+// records the copy of the capture texture into the readback buffer, after the pass
+struct CaptureReadbackNode {
+    texture: TextureId,
+}
+
+impl Node for CaptureReadbackNode {
+    fn update(
+        &mut self,
+        _world: &World,
+        resources: &Resources,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        _output: &mut ResourceSlots,
+    ) {
+        let mut readback = resources.get_mut::<CaptureReadback>().unwrap();
+        let buffer = match readback.buffer {
+            Some(buffer) if readback.requested => buffer,
+            _ => return,
+        };
+        // bevy 0.4's RenderContext can't copy a texture into a buffer, wgpu's encoder can
+        if render_context
+            .resources()
+            .downcast_ref::<WgpuRenderResourceContext>()
+            .is_none()
+        {
+            return;
+        }
+        // SAFETY: bevy_wgpu is the only backend with a WgpuRenderResourceContext and
+        // it runs every graph node with a WgpuRenderContext
+        let render_context =
+            unsafe { &mut *(render_context as *mut dyn RenderContext as *mut WgpuRenderContext) };
+        let textures = render_context
+            .render_resource_context
+            .resources
+            .textures
+            .read();
+        let buffers = render_context
+            .render_resource_context
+            .resources
+            .buffers
+            .read();
+        let (texture, buffer) = match (textures.get(&self.texture), buffers.get(&buffer)) {
+            (Some(texture), Some(buffer)) => (texture, buffer),
+            _ => return,
+        };
+        render_context
+            .command_encoder
+            .get_or_create(&render_context.device)
+            .copy_texture_to_buffer(
+                wgpu::TextureCopyView {
+                    texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::BufferCopyView {
+                    buffer,
+                    layout: wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: readback.bytes_per_row(),
+                        rows_per_image: readback.height,
+                    },
+                },
+                wgpu::Extent3d {
+                    width: readback.width,
+                    height: readback.height,
+                    depth: 1,
+                },
+            );
+        readback.requested = false;
+        readback.copied = true;
+    }
+}
+
+// padded gpu rows to an image, swapping the channels back when the texture is bgra
+fn frame_from_rows(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    bytes_per_row: u32,
+    bgra: bool,
+) -> image::RgbaImage {
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for row in data.chunks(bytes_per_row as usize).take(height as usize) {
+        for pixel in row[..(width * 4) as usize].chunks(4) {
+            if bgra {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+            } else {
+                pixels.extend_from_slice(pixel);
+            }
+        }
+    }
+    image::RgbaImage::from_raw(width, height, pixels).unwrap()
+}
+
+pub fn setup_capture(
+    commands: &mut Commands,
+    capture: Res<TailCapture>,
+    msaa: Res<Msaa>,
+    mut readback: ResMut<CaptureReadback>,
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    mut render_graph: ResMut<RenderGraph>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !capture.enabled {
+        return;
+    }
+    let size = Extent3d::new(capture.width, capture.height, 1);
+    // format and sample count have to match what the tail pipelines were built for
+    let format = TextureFormat::default();
+    // msaa textures can't be sampled or copied, the pass resolves them into one that can
+    let resolved_usage =
+        TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_SRC;
+    let color = render_resource_context.create_texture(TextureDescriptor {
+        size,
+        sample_count: msaa.samples,
+        format,
+        usage: if msaa.samples > 1 {
+            TextureUsage::OUTPUT_ATTACHMENT
+        } else {
+            resolved_usage
+        },
+        ..Default::default()
+    });
+    let resolve = if msaa.samples > 1 {
+        render_resource_context.create_texture(TextureDescriptor {
+            size,
+            format,
+            usage: resolved_usage,
+            ..Default::default()
+        })
+    } else {
+        color
+    };
+    let depth = render_resource_context.create_texture(TextureDescriptor {
+        size,
+        sample_count: msaa.samples,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Depth32Float,
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+        ..Default::default()
+    });
+    let sampler = render_resource_context.create_sampler(&SamplerDescriptor::default());
+    let texture: Handle<Texture> = CAPTURE_TEXTURE_HANDLE.typed();
+    render_resource_context.set_asset_resource(
+        &texture,
+        RenderResourceId::Texture(resolve),
+        TEXTURE_ASSET_INDEX,
+    );
+    render_resource_context.set_asset_resource(
+        &texture,
+        RenderResourceId::Sampler(sampler),
+        SAMPLER_ASSET_INDEX,
+    );
+
+    let mut pass = PassNode::<&CapturePass>::new(PassDescriptor {
+        color_attachments: vec![RenderPassColorAttachmentDescriptor {
+            attachment: TextureAttachment::Input("color_attachment".to_string()),
+            resolve_target: if msaa.samples > 1 {
+                Some(TextureAttachment::Input("color_resolve_target".to_string()))
+            } else {
+                None
+            },
+            ops: Operations {
+                load: LoadOp::Clear(Color::NONE),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: msaa.samples,
+    });
+    pass.add_camera(CAPTURE_CAMERA);
+    active_cameras.add(CAPTURE_CAMERA);
+
+    render_graph.add_system_node(CAPTURE_CAMERA, CameraNode::new(CAPTURE_CAMERA));
+    render_graph.add_node(
+        CAPTURE_TEXTURE_NODE,
+        CaptureTextureNode {
+            color,
+            depth,
+            resolve,
+        },
+    );
+    render_graph.add_node(CAPTURE_PASS, pass);
+    render_graph
+        .add_slot_edge(
+            CAPTURE_TEXTURE_NODE,
+            "color",
+            CAPTURE_PASS,
+            "color_attachment",
+        )
+        .unwrap();
+    render_graph
+        .add_slot_edge(CAPTURE_TEXTURE_NODE, "depth", CAPTURE_PASS, "depth")
+        .unwrap();
+    if msaa.samples > 1 {
+        render_graph
+            .add_slot_edge(
+                CAPTURE_TEXTURE_NODE,
+                "resolve",
+                CAPTURE_PASS,
+                "color_resolve_target",
+            )
+            .unwrap();
+    }
+    render_graph
+        .add_node_edge(CAPTURE_CAMERA, CAPTURE_PASS)
+        .unwrap();
+    // after the main pass every uniform the tail needs is already written, a quad
+    // showing the capture in the main pass sees the previous frame
+    render_graph
+        .add_node_edge(base::node::MAIN_PASS, CAPTURE_PASS)
+        .unwrap();
+
+    readback.width = capture.width;
+    readback.height = capture.height;
+    readback.buffer = Some(render_resource_context.create_buffer(BufferInfo {
+        size: (readback.bytes_per_row() * capture.height) as usize,
+        buffer_usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    }));
+    render_graph.add_node(
+        CAPTURE_READBACK_NODE,
+        CaptureReadbackNode { texture: resolve },
+    );
+    render_graph
+        .add_node_edge(CAPTURE_PASS, CAPTURE_READBACK_NODE)
+        .unwrap();
+
+    let mut camera = Camera2dBundle::default();
+    camera.camera.name = Some(CAPTURE_CAMERA.to_string());
+    commands.spawn(camera);
+
+    if capture.preview {
+        let preview = Vec2::new(capture.width as f32, capture.height as f32) * PREVIEW_SCALE;
+        let corner = Vec2::new(capture.width as f32, capture.height as f32) / 2.;
+        commands.spawn(SpriteBundle {
+            material: materials.add(ColorMaterial {
+                color: Color::WHITE,
+                texture: Some(texture),
+            }),
+            sprite: Sprite {
+                size: preview,
+                resize_mode: SpriteResizeMode::Manual,
+            },
+            transform: Transform::from_translation((corner - preview / 2.).extend(1.)),
+            ..Default::default()
+        });
+    }
+}
+
+pub fn capture_marker_system(
+    commands: &mut Commands,
+    capture: Res<TailCapture>,
+    players: Query<Entity, (With<Player>, Without<CapturePass>)>,
+    tails: Query<Entity, (With<Tail>, Without<CapturePass>)>,
+) {
+    if !capture.enabled {
+        return;
+    }
+    for entity in players.iter().chain(tails.iter()) {
+        commands.insert_one(entity, CapturePass);
+    }
+}
+
+// maps the buffer the readback node copied into last frame, that frame's commands
+// are submitted by now; waits for the gpu, fine for a still or a gif frame
+pub fn capture_readback_system(
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+    mut readback: ResMut<CaptureReadback>,
+    mut frames: ResMut<Events<CaptureFrame>>,
+) {
+    if !readback.copied {
+        return;
+    }
+    readback.copied = false;
+    let context = match render_resource_context.downcast_ref::<WgpuRenderResourceContext>() {
+        Some(context) => context,
+        None => return,
+    };
+    let buffers = context.resources.buffers.read();
+    let buffer = match readback.buffer.and_then(|buffer| buffers.get(&buffer)) {
+        Some(buffer) => buffer,
+        None => return,
+    };
+    let slice = buffer.slice(..);
+    let mapped = slice.map_async(wgpu::MapMode::Read);
+    context.device.poll(wgpu::Maintain::Wait);
+    if future::block_on(mapped).is_err() {
+        println!("failed to read the capture texture back");
+        return;
+    }
+    let bgra = matches!(
+        TextureFormat::default(),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    let frame = frame_from_rows(
+        &slice.get_mapped_range(),
+        readback.width,
+        readback.height,
+        readback.bytes_per_row(),
+        bgra,
+    );
+    buffer.unmap();
+    frames.send(CaptureFrame(Arc::new(frame)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_aligned_for_the_copy() {
+        let readback = CaptureReadback {
+            width: 1001,
+            height: 1,
+            ..Default::default()
+        };
+        assert_eq!(readback.bytes_per_row() % READBACK_ROW_ALIGNMENT, 0);
+        assert!(readback.bytes_per_row() >= 1001 * 4);
+    }
+
+    #[test]
+    fn frame_from_rows_drops_padding_and_swaps_bgra() {
+        // two rows of one pixel, padded to 8 bytes
+        let data = [1, 2, 3, 4, 9, 9, 9, 9, 5, 6, 7, 8, 9, 9, 9, 9];
+        let frame = frame_from_rows(&data, 1, 2, 8, true);
+        assert_eq!(frame.get_pixel(0, 0).0, [3, 2, 1, 4]);
+        assert_eq!(frame.get_pixel(0, 1).0, [7, 6, 5, 8]);
+        let frame = frame_from_rows(&data, 1, 2, 8, false);
+        assert_eq!(frame.get_pixel(0, 1).0, [5, 6, 7, 8]);
+    }
+}
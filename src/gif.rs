@@ -0,0 +1,149 @@
+use std::{fs::File, io::BufWriter, time::Duration};
+
+use bevy::prelude::*;
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, Frame,
+};
+
+use crate::capture::{CaptureFrame, CaptureReadback};
+
+// frames are the capture texture read back and scaled down to gif size, so
+// `--gif` turns the capture on too
+pub struct GifRecorder {
+    pub enabled: bool,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f32,
+    // recording stops by itself after this many frames
+    pub max_frames: usize,
+    since_frame: f32,
+    frames: usize,
+    // frames go into the file as they arrive, none while not recording
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+}
+
+impl Default for GifRecorder {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "tail.gif".to_string(),
+            width: 320,
+            height: 180,
+            fps: 20.,
+            max_frames: 400,
+            since_frame: 0.,
+            frames: 0,
+            encoder: None,
+        }
+    }
+}
+
+impl GifRecorder {
+    fn start(&mut self) -> image::ImageResult<()> {
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(&self.path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+        self.encoder = Some(encoder);
+        self.since_frame = f32::INFINITY;
+        self.frames = 0;
+        Ok(())
+    }
+
+    fn push(&mut self, frame: &image::RgbaImage) -> image::ImageResult<()> {
+        let delay = Delay::from_saturating_duration(Duration::from_secs_f32(1. / self.fps.max(1.)));
+        let frame = imageops::resize(frame, self.width, self.height, FilterType::Triangle);
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.encode_frame(Frame::from_parts(frame, 0, 0, delay))?;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct GifRecordState {
+    frame_reader: EventReader<CaptureFrame>,
+    // a frame was requested and hasn't come back yet
+    waiting: bool,
+}
+
+// F7 starts recording, F7 again, or running out of frames, finishes the gif
+pub fn gif_record_system(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut recorder: ResMut<GifRecorder>,
+    mut state: Local<GifRecordState>,
+    mut readback: ResMut<CaptureReadback>,
+    frames: Res<Events<CaptureFrame>>,
+) {
+    if !recorder.enabled {
+        return;
+    }
+    let state = &mut *state;
+    let toggled = keyboard_input.just_pressed(KeyCode::F7);
+    if recorder.encoder.is_none() {
+        if toggled {
+            match recorder.start() {
+                Ok(()) => info!("recording gif to {}", recorder.path),
+                Err(err) => println!("failed to create {}: {}", recorder.path, err),
+            }
+            state.waiting = false;
+        }
+        return;
+    }
+
+    if let Some(frame) = state.frame_reader.latest(&frames) {
+        if state.waiting {
+            state.waiting = false;
+            if let Err(err) = recorder.push(&frame.0) {
+                println!("failed to write gif frame to {}: {}", recorder.path, err);
+                recorder.encoder = None;
+                return;
+            }
+        }
+    }
+    recorder.since_frame += time.delta_seconds();
+    if recorder.since_frame >= 1. / recorder.fps && !state.waiting {
+        recorder.since_frame = 0.;
+        readback.request();
+        state.waiting = true;
+    }
+    if !toggled && recorder.frames < recorder.max_frames {
+        return;
+    }
+
+    // dropping the encoder writes the trailer
+    recorder.encoder = None;
+    info!("wrote {} frames to {}", recorder.frames, recorder.path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorder_writes_a_looping_gif() {
+        let path = std::env::temp_dir().join("bevy_rainbow_test.gif");
+        let mut recorder = GifRecorder {
+            path: path.to_string_lossy().into_owned(),
+            width: 16,
+            height: 9,
+            ..Default::default()
+        };
+        recorder.start().unwrap();
+        let frame = image::RgbaImage::from_pixel(64, 36, image::Rgba([255, 0, 0, 255]));
+        recorder.push(&frame).unwrap();
+        recorder.push(&frame).unwrap();
+        recorder.encoder = None;
+        assert_eq!(recorder.frames, 2);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(u16::from_le_bytes([bytes[6], bytes[7]]), 16);
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), 9);
+        assert_eq!(bytes.last(), Some(&0x3b));
+        std::fs::remove_file(&path).ok();
+    }
+}
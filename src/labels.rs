@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{MainCamera, Player};
+
+pub struct PlayerName(pub String);
+
+// `--labels`, the font and size with `--label-font` and `--label-size`
+pub struct LabelConfig {
+    pub enabled: bool,
+    // given to the player spawned at startup, `--player-name`
+    pub player_name: String,
+    // under the asset folder, labels stay blank if it's missing
+    pub font: String,
+    pub size: f32,
+    pub color: Color,
+    // world units between the player's center and the bottom of its label
+    pub offset: f32,
+}
+
+impl Default for LabelConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            player_name: "player 1".to_string(),
+            font: "fonts/FiraSans-Bold.ttf".to_string(),
+            size: 20.,
+            color: Color::WHITE,
+            offset: 60.,
+        }
+    }
+}
+
+// ui text, moved every frame to wherever its player shows up on screen
+pub struct PlayerLabel {
+    player: Entity,
+}
+
+pub fn player_label_system(
+    commands: &mut Commands,
+    config: Res<LabelConfig>,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    mut labeled: Local<HashMap<Entity, Entity>>,
+    players: Query<(Entity, &Transform, &PlayerName), With<Player>>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    mut labels: Query<(Entity, &PlayerLabel, &mut Style, &mut Text, &Node)>,
+) {
+    if !config.enabled {
+        return;
+    }
+    for (player, _, name) in players.iter() {
+        if labeled.contains_key(&player) {
+            continue;
+        }
+        let label = commands
+            .spawn(TextBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text: Text {
+                    value: name.0.clone(),
+                    font: asset_server.load(config.font.as_str()),
+                    style: TextStyle {
+                        font_size: config.size,
+                        color: config.color,
+                        ..Default::default()
+                    },
+                },
+                ..Default::default()
+            })
+            .with(PlayerLabel { player })
+            .current_entity()
+            .unwrap();
+        labeled.insert(player, label);
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let half_window = Vec2::new(window.width(), window.height()) / 2.;
+    // follow and shake only move the camera, its scale stands in for zoom
+    let (camera, zoom) = match cameras.iter().next() {
+        Some(trans) => (
+            Vec2::new(trans.translation.x, trans.translation.y),
+            trans.scale.x.max(f32::EPSILON),
+        ),
+        None => (Vec2::zero(), 1.),
+    };
+    for (entity, label, mut style, mut text, node) in labels.iter_mut() {
+        let (trans, name) = match players.get(label.player) {
+            Ok((_, trans, name)) => (trans, name),
+            Err(_) => {
+                labeled.remove(&label.player);
+                commands.despawn(entity);
+                continue;
+            }
+        };
+        if text.value != name.0 {
+            text.value = name.0.clone();
+        }
+        let world = Vec2::new(trans.translation.x, trans.translation.y + config.offset);
+        // ui positions start at the bottom left corner of the window
+        let screen = (world - camera) / zoom + half_window;
+        style.position.left = Val::Px(screen.x - node.size.x / 2.);
+        style.position.bottom = Val::Px(screen.y);
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{make_mesh, modify_mesh, Player, TailLayers, TailNode, Vertice};
+
+// the tail closes into a loop when its head comes back within threshold of its
+// end, the enclosed area is then filled
+pub struct Lasso {
+    pub enabled: bool,
+    pub threshold: f32,
+    // fewer nodes than this are never a loop, the head and end start out together
+    pub min_nodes: usize,
+    pub color: Color,
+}
+
+impl Default for Lasso {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 40.,
+            min_nodes: 12,
+            color: Color::rgba(1., 1., 1., 0.3),
+        }
+    }
+}
+
+// sent once each time a player's loop closes
+pub struct LassoClosedEvent {
+    pub player: Entity,
+    pub area: f32,
+}
+
+pub struct LassoFill {
+    player: Entity,
+    closed: bool,
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+// shoelace, positive when counterclockwise
+fn signed_area(points: &[Vec2]) -> f32 {
+    let len = points.len();
+    (0..len)
+        .map(|i| cross(points[i], points[(i + 1) % len]))
+        .sum::<f32>()
+        / 2.
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross(b - a, p - a) >= 0. && cross(c - b, p - b) >= 0. && cross(a - c, p - c) >= 0.
+}
+
+// ear clipping, None when no ear is left, which a self-crossing loop runs into
+fn triangulate(points: &[Vec2]) -> Option<Vec<u16>> {
+    if points.len() < 3 {
+        return None;
+    }
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if signed_area(points) < 0. {
+        remaining.reverse();
+    }
+    let mut indices = vec![];
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let mut clipped = false;
+        for i in 0..len {
+            let (prev, cur, next) = (
+                remaining[(i + len - 1) % len],
+                remaining[i],
+                remaining[(i + 1) % len],
+            );
+            let (a, b, c) = (points[prev], points[cur], points[next]);
+            let turn = cross(b - a, c - b);
+            // a straight run adds no area, the middle point can just go
+            if turn.abs() < f32::EPSILON {
+                remaining.remove(i);
+                clipped = true;
+                break;
+            }
+            if turn < 0. {
+                continue;
+            }
+            let blocked = remaining.iter().any(|&j| {
+                let p = points[j];
+                j != prev
+                    && j != cur
+                    && j != next
+                    && p != a
+                    && p != b
+                    && p != c
+                    && in_triangle(p, a, b, c)
+            });
+            if blocked {
+                continue;
+            }
+            indices.extend(&[prev as u16, cur as u16, next as u16]);
+            remaining.remove(i);
+            clipped = true;
+            break;
+        }
+        if !clipped {
+            return None;
+        }
+    }
+    indices.extend(remaining.iter().map(|&i| i as u16));
+    Some(indices)
+}
+
+// the loop's outline without the bunched up nodes left while standing still
+fn lasso_points(tail: &[TailNode]) -> Vec<Vec2> {
+    let mut points: Vec<Vec2> = vec![];
+    for node in tail {
+        if points
+            .last()
+            .is_none_or(|last| last.distance(node.pos) > f32::EPSILON)
+        {
+            points.push(node.pos);
+        }
+    }
+    points
+}
+
+pub fn lasso_system(
+    commands: &mut Commands,
+    lasso: Res<Lasso>,
+    layers: Res<TailLayers>,
+    mut fills: Local<HashMap<Entity, Entity>>,
+    mut closed_events: ResMut<Events<LassoClosedEvent>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<(Entity, &Player)>,
+    mut query: Query<(Entity, &mut LassoFill, &Handle<Mesh>, &mut Visible)>,
+) {
+    if !lasso.enabled {
+        return;
+    }
+    for (player, _) in players.iter() {
+        if fills.contains_key(&player) {
+            continue;
+        }
+        let fill = commands
+            .spawn(SpriteBundle {
+                mesh: meshes.add(make_mesh(&[], vec![])),
+                material: materials.add(lasso.color.into()),
+                sprite: Sprite {
+                    size: Vec2::new(1.0, 1.0),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(Vec3::new(0., 0., layers.tail - 1.5)),
+                visible: Visible {
+                    is_visible: false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .with(LassoFill {
+                player,
+                closed: false,
+            })
+            .current_entity()
+            .unwrap();
+        fills.insert(player, fill);
+    }
+
+    for (entity, mut fill, mesh, mut visible) in query.iter_mut() {
+        let player = match players.get(fill.player) {
+            Ok((_, player)) => player,
+            Err(_) => {
+                fills.remove(&fill.player);
+                commands.despawn(entity);
+                continue;
+            }
+        };
+        let tail = &player.tail;
+        // a loop broken by a teleport doesn't enclose anything
+        let closes = tail.len() >= lasso.min_nodes
+            && !tail.iter().any(|node| node.gap)
+            && tail[0].pos.distance(tail[tail.len() - 1].pos) <= lasso.threshold;
+        let points = lasso_points(tail);
+        let triangles = if closes { triangulate(&points) } else { None };
+        let triangles = match (triangles, meshes.get_mut(mesh)) {
+            (Some(triangles), Some(mesh)) => {
+                let vertices: Vec<Vertice> = points
+                    .iter()
+                    .map(|pos| ([pos.x, pos.y, 0.], [0., 0., 1.], [0., 0.]))
+                    .collect();
+                modify_mesh(mesh, &vertices, triangles);
+                true
+            }
+            _ => false,
+        };
+        visible.is_visible = triangles;
+        if triangles && !fill.closed {
+            let area = signed_area(&points).abs();
+            info!("lasso closed, area {}", area);
+            closed_events.send(LassoClosedEvent {
+                player: fill.player,
+                area,
+            });
+        }
+        fill.closed = triangles;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::Stage;
+
+    use super::*;
+
+    fn square() -> Vec<Vec2> {
+        vec![
+            Vec2::new(0., 0.),
+            Vec2::new(10., 0.),
+            Vec2::new(10., 10.),
+            Vec2::new(0., 10.),
+        ]
+    }
+
+    fn triangles_area(points: &[Vec2], indices: &[u16]) -> f32 {
+        indices
+            .chunks(3)
+            .map(|t| {
+                let (a, b, c) = (
+                    points[t[0] as usize],
+                    points[t[1] as usize],
+                    points[t[2] as usize],
+                );
+                cross(b - a, c - a) / 2.
+            })
+            .sum()
+    }
+
+    #[test]
+    fn square_loop_area() {
+        let mut points = square();
+        assert_eq!(signed_area(&points), 100.);
+        points.reverse();
+        assert_eq!(signed_area(&points), -100.);
+    }
+
+    #[test]
+    fn concave_loop_is_clipped_into_ears() {
+        // an L, the reflex corner at (5, 5) can't be an ear
+        let points = vec![
+            Vec2::new(0., 0.),
+            Vec2::new(10., 0.),
+            Vec2::new(10., 5.),
+            Vec2::new(5., 5.),
+            Vec2::new(5., 10.),
+            Vec2::new(0., 10.),
+        ];
+        let indices = triangulate(&points).unwrap();
+        assert_eq!(indices.len(), (points.len() - 2) * 3);
+        assert!(indices.chunks(3).all(|t| {
+            let (a, b, c) = (
+                points[t[0] as usize],
+                points[t[1] as usize],
+                points[t[2] as usize],
+            );
+            cross(b - a, c - b) > 0.
+        }));
+        assert_eq!(triangles_area(&points, &indices), 75.);
+    }
+
+    fn lasso_app(tail: Vec<TailNode>) -> (AppBuilder, Entity) {
+        let mut app = App::build();
+        app.add_plugin(bevy::reflect::ReflectPlugin)
+            .add_plugin(bevy::core::CorePlugin)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<ColorMaterial>()
+            .add_event::<LassoClosedEvent>()
+            .add_resource(Lasso {
+                enabled: true,
+                min_nodes: 4,
+                ..Default::default()
+            })
+            .add_resource(TailLayers {
+                player: 2.,
+                tail: 1.,
+                render_priority: 0,
+            });
+        let mut player = Player::full(0);
+        player.tail = tail;
+        let player = app.app.world.spawn((player,));
+        (app, player)
+    }
+
+    // whether the fill is drawn after one more lasso_system run
+    fn run_lasso(app: &mut AppBuilder) -> bool {
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mut stage = SystemStage::single(lasso_system.system());
+        stage.initialize(world, resources);
+        stage.run(world, resources);
+        world
+            .query::<(&LassoFill, &Visible)>()
+            .next()
+            .is_some_and(|(_, visible)| visible.is_visible)
+    }
+
+    fn closed_count(app: &AppBuilder) -> usize {
+        let events = app.app.resources.get::<Events<LassoClosedEvent>>().unwrap();
+        events.iter_current_update_events().count()
+    }
+
+    #[test]
+    fn fill_shows_once_the_loop_closes() {
+        let node = |x: f32, y: f32| TailNode {
+            pos: Vec2::new(x, y),
+            ..Default::default()
+        };
+        let open = vec![
+            node(0., 0.),
+            node(100., 0.),
+            node(100., 100.),
+            node(0., 100.),
+            node(0., 200.),
+        ];
+        let (mut app, player) = lasso_app(open);
+        assert!(!run_lasso(&mut app));
+        assert_eq!(closed_count(&app), 0);
+
+        // the head comes back next to the end
+        app.app.world.get_mut::<Player>(player).unwrap().tail[4] = node(0., 10.);
+        assert!(run_lasso(&mut app));
+        let events = app.app.resources.get::<Events<LassoClosedEvent>>().unwrap();
+        let closed: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].player, player);
+        assert_eq!(closed[0].area, 10000.);
+    }
+}
@@ -0,0 +1,6428 @@
+// bevy systems take their resources and queries as arguments
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod audio;
+mod blur;
+mod capture;
+mod gif;
+mod labels;
+mod lasso;
+mod markers;
+mod mask;
+mod minimap;
+mod palette;
+mod save;
+mod screenshot;
+mod shockwave;
+mod snapshot;
+mod sprites;
+mod timer_bar;
+
+pub use lasso::LassoClosedEvent;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{
+    asset::HandleId,
+    input::mouse::MouseMotion,
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::PerspectiveProjection,
+        mesh::{Indices, VertexAttributeValues},
+        pipeline::PrimitiveTopology,
+        pipeline::{CullMode, PipelineDescriptor, RasterizationStateDescriptor, RenderPipeline},
+        render_graph::{base, AssetRenderResourcesNode, RenderGraph},
+        renderer::RenderResources,
+        shader::{ShaderStage, ShaderStages},
+    },
+    window::WindowFocused,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+pub const SIZE: f32 = 100.;
+
+#[derive(Clone, RenderResources, TypeUuid)]
+#[uuid = "0320b9b8-b3a3-4baa-8bfa-c94008177b17"]
+struct MyMaterialWithVertexColorSupport {
+    global_alpha: f32,
+    palette: Vec<Vec4>,
+    intensity: f32,
+    // opacity at 0, 1/3, 2/3 and 1 of the way down the tail
+    alpha_curve: Vec4,
+    // rainbow repeats down the tail this many times, 0 colors across the width
+    length_cycles: f32,
+    // used instead of the palette where Vertex_X is negative, see IdleColor
+    idle_color: Vec4,
+    // ColorInterpolation as a float
+    interp_mode: f32,
+    // what old nodes fade toward, see TrailDecay
+    decay_color: Vec4,
+    // replaces the palette by its alpha, see SolidColor
+    solid_color: Vec4,
+}
+
+impl Default for MyMaterialWithVertexColorSupport {
+    fn default() -> Self {
+        Self {
+            global_alpha: 1.0,
+            palette: palette::rainbow_stops().to_vec(),
+            intensity: 1.0,
+            alpha_curve: Vec4::one(),
+            length_cycles: 0.,
+            idle_color: Vec4::new(0.5, 0.5, 0.5, 1.0),
+            interp_mode: ColorInterpolation::Linear.uniform(),
+            decay_color: Vec4::one(),
+            solid_color: Vec4::zero(),
+        }
+    }
+}
+
+fn set_global_alpha(
+    materials: &mut Assets<MyMaterialWithVertexColorSupport>,
+    handle: &Handle<MyMaterialWithVertexColorSupport>,
+    alpha: f32,
+) {
+    if let Some(material) = materials.get_mut(handle) {
+        material.global_alpha = alpha.clamp(0.0, 1.0);
+    }
+}
+
+const VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in float Vertex_X;
+layout(location = 2) in float Vertex_A;
+layout(location = 3) in float Vertex_Y;
+layout(location = 4) in float Vertex_N;
+layout(location = 5) in float Vertex_D;
+layout(location = 0) out float v_x;
+layout(location = 1) out float v_a;
+layout(location = 2) out float v_y;
+layout(location = 3) out float v_n;
+layout(location = 4) out float v_d;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+    v_x = Vertex_X;
+    v_a = Vertex_A;
+    v_y = Vertex_Y;
+    v_n = Vertex_N;
+    v_d = Vertex_D;
+}
+"#;
+
+// VERTEX_SHADER for meshes from pack_ribbon_attributes, same outputs
+const PACKED_VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in uint Vertex_P;
+layout(location = 2) in float Vertex_Y;
+layout(location = 3) in float Vertex_N;
+layout(location = 0) out float v_x;
+layout(location = 1) out float v_a;
+layout(location = 2) out float v_y;
+layout(location = 3) out float v_n;
+layout(location = 4) out float v_d;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+    float x = float(Vertex_P & 255u);
+    v_x = x == 0.0 ? -1.0 : (x - 1.0) / 254.0;
+    v_a = float((Vertex_P >> 8u) & 255u) / 255.0;
+    v_d = float((Vertex_P >> 16u) & 255u) / 255.0;
+    v_y = Vertex_Y;
+    v_n = Vertex_N;
+}
+"#;
+
+// example replacement for VERTEX_SHADER, ripples the ribbon along its length
+const WOBBLE_VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in float Vertex_X;
+layout(location = 2) in float Vertex_A;
+layout(location = 3) in float Vertex_Y;
+layout(location = 4) in float Vertex_N;
+layout(location = 5) in float Vertex_D;
+layout(location = 0) out float v_x;
+layout(location = 1) out float v_a;
+layout(location = 2) out float v_y;
+layout(location = 3) out float v_n;
+layout(location = 4) out float v_d;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    float phase = Vertex_Y * 25.0;
+    vec3 wobble = vec3(cos(phase), sin(phase), 0.0) * 8.0 * Vertex_Y;
+    gl_Position = ViewProj * Model * vec4(Vertex_Position + wobble, 1.0);
+    v_x = Vertex_X;
+    v_a = Vertex_A;
+    v_y = Vertex_Y;
+    v_n = Vertex_N;
+    v_d = Vertex_D;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+#version 450
+layout(location = 0) out vec4 o_Target;
+layout(location = 0) in float v_x;
+layout(location = 1) in float v_a;
+layout(location = 2) in float v_y;
+layout(location = 3) in float v_n;
+layout(location = 4) in float v_d;
+layout(set = 2, binding = 0) uniform MyMaterialWithVertexColorSupport_global_alpha {
+    float global_alpha;
+};
+
+layout(set = 2, binding = 1) uniform MyMaterialWithVertexColorSupport_palette {
+    vec4 palette[6];
+};
+layout(set = 2, binding = 2) uniform MyMaterialWithVertexColorSupport_intensity {
+    float intensity;
+};
+layout(set = 2, binding = 3) uniform MyMaterialWithVertexColorSupport_alpha_curve {
+    vec4 alpha_curve;
+};
+layout(set = 2, binding = 4) uniform MyMaterialWithVertexColorSupport_length_cycles {
+    float length_cycles;
+};
+layout(set = 2, binding = 5) uniform MyMaterialWithVertexColorSupport_idle_color {
+    vec4 idle_color;
+};
+layout(set = 2, binding = 6) uniform MyMaterialWithVertexColorSupport_interp_mode {
+    float interp_mode;
+};
+layout(set = 2, binding = 7) uniform MyMaterialWithVertexColorSupport_decay_color {
+    vec4 decay_color;
+};
+layout(set = 2, binding = 8) uniform MyMaterialWithVertexColorSupport_solid_color {
+    vec4 solid_color;
+};
+
+// piecewise linear through 4 evenly spaced control points along the tail
+float curve_alpha(float t)
+{
+    float scaled = clamp(t, 0.0, 1.0) * 3.0;
+    int i = int(min(floor(scaled), 2.0));
+    return mix(alpha_curve[i], alpha_curve[i + 1], scaled - float(i));
+}
+
+vec3 rainbow(float x)
+{
+    int level = int(clamp(floor(x * 6.0), 0.0, 5.0));
+    return palette[level].rgb;
+}
+
+float color_coord(float x, float y)
+{
+    return length_cycles > 0.0 ? fract(y * length_cycles) : x;
+}
+
+// v_x between two nodes, re-eased by interp_mode; n is the node index, so within a
+// segment x changes by a fixed amount per node that the derivatives give back
+float interp_x(float x, float n, vec2 dx, vec2 dn)
+{
+    float len2 = dot(dn, dn);
+    if (interp_mode < 0.5 || len2 < 1e-8) {
+        return x;
+    }
+    float f = fract(n);
+    float per_node = dot(dx, dn) / len2;
+    float eased = interp_mode < 1.5 ? step(0.5, f) : smoothstep(0.0, 1.0, f);
+    return x + (eased - f) * per_node;
+}
+
+void main() {
+    // derivatives first, they're undefined inside the branches below
+    vec2 dx = vec2(dFdx(v_x), dFdy(v_x));
+    vec2 dn = vec2(dFdx(v_n), dFdy(v_n));
+    float x = interp_x(v_x, v_n, dx, dn);
+    // negative v_x marks nodes that were barely moving
+    vec3 color = v_x < 0.0
+        ? idle_color.rgb
+        : mix(rainbow(color_coord(x, v_y)), solid_color.rgb, solid_color.a);
+    // v_d is how far the node has aged toward decay_color
+    o_Target = vec4(
+        mix(color * intensity, decay_color.rgb, v_d),
+        v_a * curve_alpha(v_y) * global_alpha * mix(1.0, decay_color.a, v_d)
+    );
+}
+"#;
+
+// length_cycles the tail material starts with
+struct LengthCycles(f32);
+
+// custom stages replace the built-in ones, a vertex stage has to keep passing
+// Vertex_X, Vertex_A, Vertex_Y, Vertex_N and Vertex_D through as v_x, v_a, v_y, v_n
+// and v_d
+#[derive(Default)]
+struct TailShaders {
+    vertex: Option<String>,
+    fragment: Option<String>,
+}
+
+fn make_rainbow_pipeline(
+    vertex_src: &str,
+    fragment_src: &str,
+    shaders: &mut Assets<Shader>,
+    pipelines: &mut Assets<PipelineDescriptor>,
+) -> Handle<PipelineDescriptor> {
+    let stages = ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, vertex_src)),
+        fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, fragment_src))),
+    };
+    add_rainbow_pipeline(stages, pipelines)
+}
+
+fn add_rainbow_pipeline(
+    stages: ShaderStages,
+    pipelines: &mut Assets<PipelineDescriptor>,
+) -> Handle<PipelineDescriptor> {
+    let mut pipeline_setting = PipelineDescriptor::default_config(stages);
+    pipeline_setting
+        .rasterization_state
+        .replace(RasterizationStateDescriptor {
+            cull_mode: CullMode::None,
+            ..Default::default()
+        });
+    pipelines.add(pipeline_setting)
+}
+
+// looked up under the asset folder, edits show up live with --hot-reload
+const TAIL_VERTEX_SHADER_FILE: &str = "shaders/tail.vert";
+const TAIL_FRAGMENT_SHADER_FILE: &str = "shaders/tail.frag";
+
+struct ShaderHotReload(bool);
+
+// same root the AssetServer reads from
+fn asset_root() -> PathBuf {
+    std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        })
+        .unwrap_or_default()
+        .join("assets")
+}
+
+// a source given on the command line wins, then the asset file, then the built-in
+fn tail_shader_stage(
+    stage: ShaderStage,
+    source: Option<&str>,
+    file: &str,
+    builtin: &str,
+    shaders: &mut Assets<Shader>,
+    asset_server: &AssetServer,
+) -> Handle<Shader> {
+    match source {
+        Some(source) => shaders.add(Shader::from_glsl(stage, source)),
+        None if asset_root().join(file).exists() => asset_server.load(file),
+        None => shaders.add(Shader::from_glsl(stage, builtin)),
+    }
+}
+
+const VERTEX_SHADER_3D: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec3 Vertex_Normal;
+layout(location = 2) in float Vertex_X;
+layout(location = 3) in float Vertex_A;
+layout(location = 4) in float Vertex_Y;
+layout(location = 5) in float Vertex_N;
+layout(location = 6) in float Vertex_D;
+layout(location = 0) out float v_x;
+layout(location = 1) out float v_a;
+layout(location = 2) out vec3 v_normal;
+layout(location = 3) out float v_y;
+layout(location = 4) out float v_n;
+layout(location = 5) out float v_d;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+    v_normal = mat3(Model) * Vertex_Normal;
+    v_x = Vertex_X;
+    v_a = Vertex_A;
+    v_y = Vertex_Y;
+    v_n = Vertex_N;
+    v_d = Vertex_D;
+}
+"#;
+
+// FRAGMENT_SHADER's colors, lit
+const FRAGMENT_SHADER_3D: &str = r#"
+#version 450
+layout(location = 0) out vec4 o_Target;
+layout(location = 0) in float v_x;
+layout(location = 1) in float v_a;
+layout(location = 2) in vec3 v_normal;
+layout(location = 3) in float v_y;
+layout(location = 4) in float v_n;
+layout(location = 5) in float v_d;
+layout(set = 2, binding = 0) uniform MyMaterialWithVertexColorSupport_global_alpha {
+    float global_alpha;
+};
+
+layout(set = 2, binding = 1) uniform MyMaterialWithVertexColorSupport_palette {
+    vec4 palette[6];
+};
+layout(set = 2, binding = 2) uniform MyMaterialWithVertexColorSupport_intensity {
+    float intensity;
+};
+layout(set = 2, binding = 3) uniform MyMaterialWithVertexColorSupport_alpha_curve {
+    vec4 alpha_curve;
+};
+layout(set = 2, binding = 4) uniform MyMaterialWithVertexColorSupport_length_cycles {
+    float length_cycles;
+};
+layout(set = 2, binding = 5) uniform MyMaterialWithVertexColorSupport_idle_color {
+    vec4 idle_color;
+};
+layout(set = 2, binding = 6) uniform MyMaterialWithVertexColorSupport_interp_mode {
+    float interp_mode;
+};
+layout(set = 2, binding = 7) uniform MyMaterialWithVertexColorSupport_decay_color {
+    vec4 decay_color;
+};
+layout(set = 2, binding = 8) uniform MyMaterialWithVertexColorSupport_solid_color {
+    vec4 solid_color;
+};
+
+// piecewise linear through 4 evenly spaced control points along the tail
+float curve_alpha(float t)
+{
+    float scaled = clamp(t, 0.0, 1.0) * 3.0;
+    int i = int(min(floor(scaled), 2.0));
+    return mix(alpha_curve[i], alpha_curve[i + 1], scaled - float(i));
+}
+
+vec3 rainbow(float x)
+{
+    int level = int(clamp(floor(x * 6.0), 0.0, 5.0));
+    return palette[level].rgb;
+}
+
+float color_coord(float x, float y)
+{
+    return length_cycles > 0.0 ? fract(y * length_cycles) : x;
+}
+
+// v_x between two nodes, re-eased by interp_mode; n is the node index, so within a
+// segment x changes by a fixed amount per node that the derivatives give back
+float interp_x(float x, float n, vec2 dx, vec2 dn)
+{
+    float len2 = dot(dn, dn);
+    if (interp_mode < 0.5 || len2 < 1e-8) {
+        return x;
+    }
+    float f = fract(n);
+    float per_node = dot(dx, dn) / len2;
+    float eased = interp_mode < 1.5 ? step(0.5, f) : smoothstep(0.0, 1.0, f);
+    return x + (eased - f) * per_node;
+}
+
+void main() {
+    vec2 dx = vec2(dFdx(v_x), dFdy(v_x));
+    vec2 dn = vec2(dFdx(v_n), dFdy(v_n));
+    float x = interp_x(v_x, v_n, dx, dn);
+    vec3 color = v_x < 0.0
+        ? idle_color.rgb
+        : mix(rainbow(color_coord(x, v_y)), solid_color.rgb, solid_color.a);
+    // two sided so side faces light up the same whichever way they were wound
+    vec3 light_dir = normalize(vec3(0.4, 0.6, 1.0));
+    float shade = 0.4 + 0.6 * abs(dot(normalize(v_normal), light_dir));
+    o_Target = vec4(
+        mix(color * shade * intensity, decay_color.rgb, v_d),
+        v_a * curve_alpha(v_y) * global_alpha * mix(1.0, decay_color.a, v_d)
+    );
+}
+"#;
+
+pub type Vertice = ([f32; 3], [f32; 3], [f32; 2]);
+
+fn vec2_to_array_3(vec: Vec2) -> [f32; 3] {
+    [vec.x, vec.y, 0.0]
+}
+
+fn modify_mesh(mesh: &mut Mesh, vertices: &[Vertice], indices: Vec<u16>) {
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    for (position, normal, uv) in vertices.iter() {
+        positions.push(*position);
+        normals.push(*normal);
+        uvs.push(*uv);
+    }
+    debug_assert!(
+        positions.iter().flatten().all(|v| v.is_finite()),
+        "non-finite vertex position"
+    );
+    mesh.set_indices(Some(Indices::U16(indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+}
+
+pub fn make_mesh(vertices: &[Vertice], indices: Vec<u16>) -> Mesh {
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    modify_mesh(&mut mesh, vertices, indices);
+    mesh
+}
+
+fn make_player_mesh(size: f32) -> Mesh {
+    let indices = vec![0, 2, 1, 2, 0, 3];
+    let vertices = &[
+        ([-size / 2., -size / 2., 0.], [0., 0., 1.], [0., 0.]),
+        ([-size / 2., size / 2., 0.0], [0., 0., 1.], [0., 0.]),
+        ([size / 2., size / 2., 0.0], [0., 0., 1.], [0., 0.]),
+        ([size / 2., -size / 2., 0.0], [0., 0., 1.], [0., 0.]),
+    ];
+    make_mesh(vertices, indices)
+}
+
+// fan around the center, segments triangles per corner; a radius of 0 is the square
+fn make_rounded_rect_mesh(size: f32, corner_radius: f32, segments: usize) -> Mesh {
+    let radius = corner_radius.min(size / 2.);
+    if radius <= 0. || segments == 0 {
+        return make_player_mesh(size);
+    }
+    let vertex = |pos: Vec2| -> Vertice {
+        let uv = [pos.x / size + 0.5, 0.5 - pos.y / size];
+        ([pos.x, pos.y, 0.], [0., 0., 1.], uv)
+    };
+    let inner = size / 2. - radius;
+    let mut vertices = vec![vertex(Vec2::zero())];
+    // counterclockwise from the bottom right corner, like the square's winding
+    let corners = [
+        Vec2::new(inner, -inner),
+        Vec2::new(inner, inner),
+        Vec2::new(-inner, inner),
+        Vec2::new(-inner, -inner),
+    ];
+    for (i, center) in corners.iter().enumerate() {
+        let start = -std::f32::consts::FRAC_PI_2 + i as f32 * std::f32::consts::FRAC_PI_2;
+        for step in 0..=segments {
+            let angle = start + step as f32 / segments as f32 * std::f32::consts::FRAC_PI_2;
+            vertices.push(vertex(
+                *center + Vec2::new(angle.cos(), angle.sin()) * radius,
+            ));
+        }
+    }
+    let rim = vertices.len() as u16 - 1;
+    let indices = (1..=rim).flat_map(|i| vec![0, i, i % rim + 1]).collect();
+    make_mesh(&vertices, indices)
+}
+
+// corner_radius 0 keeps the sharp quad
+struct PlayerShape {
+    corner_radius: f32,
+    segments: usize,
+}
+
+impl Default for PlayerShape {
+    fn default() -> Self {
+        Self {
+            corner_radius: 0.,
+            segments: 8,
+        }
+    }
+}
+
+struct MousePos(Vec2);
+
+// where setup spawns the player, --initial-position x,y; the cursor counts as
+// resting there until it first moves, or the player would head straight for the origin
+#[derive(Default)]
+struct InitialPosition(Vec2);
+
+// cursor speed in pixels per second, measured once per frame
+#[derive(Default)]
+struct MouseVelocity(Vec2);
+
+// seconds to extrapolate the cursor ahead, trades lag for overshoot on turns
+struct MousePrediction {
+    time: f32,
+}
+
+struct TailTimer(Timer);
+
+struct HitchGuard {
+    // frames longer than this, in seconds, are treated as a hitch
+    threshold: f32,
+    // a jump this far across a hitch starts a new tail instead of a streak
+    max_jump: f32,
+}
+
+impl Default for HitchGuard {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_jump: 200.,
+        }
+    }
+}
+
+impl HitchGuard {
+    fn is_hitch(&self, time: &Time) -> bool {
+        time.delta_seconds() > self.threshold
+    }
+}
+
+// lerp the drawn player between the last two tail ticks
+struct RenderInterpolation(bool);
+
+#[derive(Default)]
+struct SimPosition {
+    previous: Vec2,
+    current: Vec2,
+}
+
+struct TailFade {
+    target: f32,
+    speed: f32,
+}
+
+impl Default for TailFade {
+    fn default() -> Self {
+        Self {
+            target: 1.0,
+            speed: 2.0,
+        }
+    }
+}
+
+// --focus-dim, losing focus fades the tails toward alpha through TailFade and
+// stops tail_gen_system until focus comes back
+struct FocusDim {
+    enabled: bool,
+    alpha: f32,
+}
+
+impl Default for FocusDim {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            alpha: 0.3,
+        }
+    }
+}
+
+#[derive(Default)]
+struct WindowUnfocused(bool);
+
+#[derive(Default)]
+struct FocusDimState {
+    focused_event_reader: EventReader<WindowFocused>,
+    // TailFade::target from before the window lost focus
+    faded_from: Option<f32>,
+}
+
+fn focus_dim_system(
+    focus_dim: Res<FocusDim>,
+    mut state: Local<FocusDimState>,
+    focused_events: Res<Events<WindowFocused>>,
+    mut unfocused: ResMut<WindowUnfocused>,
+    mut fade: ResMut<TailFade>,
+) {
+    let state = &mut *state;
+    for event in state.focused_event_reader.iter(&focused_events) {
+        if !focus_dim.enabled {
+            continue;
+        }
+        unfocused.0 = !event.focused;
+        if event.focused {
+            if let Some(target) = state.faded_from.take() {
+                fade.target = target;
+            }
+        } else if state.faded_from.is_none() {
+            state.faded_from = Some(fade.target);
+            fade.target = fade.target.min(focus_dim.alpha);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Velocity {
+    value: Vec2,
+    last_position: Vec2,
+}
+
+// color of the player quad only, the tail keeps its own material
+struct PlayerColor(Color);
+
+// the world camera, as opposed to the ui camera
+struct MainCamera;
+
+// everything random draws from here, --seed makes a run repeatable
+struct RngResource(StdRng);
+
+impl RngResource {
+    fn new(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => Self(StdRng::seed_from_u64(seed)),
+            None => Self(StdRng::from_entropy()),
+        }
+    }
+}
+
+struct CameraShake {
+    // world units of offset at full trauma
+    intensity: f32,
+    // trauma lost per second
+    decay: f32,
+    // radians between frame velocities that count as whipping around
+    angle_threshold: f32,
+    min_speed: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            intensity: 12.,
+            decay: 2.,
+            angle_threshold: std::f32::consts::FRAC_PI_2,
+            min_speed: 300.,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShakeState {
+    trauma: f32,
+    offset: Vec2,
+    last_direction: Vec2,
+}
+
+struct Arena {
+    min: Vec2,
+    max: Vec2,
+    // --clip-arena, the ribbon ends at the edge instead of following the player out
+    clip_tail: bool,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self {
+            min: Vec2::new(-640., -360.),
+            max: Vec2::new(640., 360.),
+            clip_tail: false,
+        }
+    }
+}
+
+impl Arena {
+    fn contains(&self, pos: Vec2) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    // Liang-Barsky, the part of a to b inside as fractions of the way from a
+    fn clip_segment(&self, a: Vec2, b: Vec2) -> Option<(f32, f32)> {
+        let d = b - a;
+        let (mut t0, mut t1) = (0f32, 1f32);
+        for &(p, q) in &[
+            (-d.x, a.x - self.min.x),
+            (d.x, self.max.x - a.x),
+            (-d.y, a.y - self.min.y),
+            (d.y, self.max.y - a.y),
+        ] {
+            if p == 0. {
+                if q < 0. {
+                    return None;
+                }
+                continue;
+            }
+            let t = q / p;
+            if p < 0. {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+        (t0 <= t1).then_some((t0, t1))
+    }
+}
+
+// cell_size 0 keeps the tail free-form
+#[derive(Default)]
+struct TailGrid {
+    cell_size: f32,
+}
+
+impl TailGrid {
+    fn snap(&self, pos: Vec2) -> Vec2 {
+        if self.cell_size <= 0. {
+            return pos;
+        }
+        (pos / self.cell_size).round() * self.cell_size
+    }
+}
+
+#[derive(Default)]
+struct TailCollision {
+    enabled: bool,
+}
+
+// no collisions for a player, or against its tail, this long after it spawns
+struct SpawnGrace {
+    duration: f32,
+    // blinks per second while it lasts
+    blink_rate: f32,
+}
+
+impl Default for SpawnGrace {
+    fn default() -> Self {
+        Self {
+            duration: 1.,
+            blink_rate: 8.,
+        }
+    }
+}
+
+// seconds_since_startup when the player first showed up
+struct SpawnTime(f64);
+
+impl SpawnTime {
+    fn in_grace(&self, time: &Time, grace: &SpawnGrace) -> bool {
+        time.seconds_since_startup() - self.0 < grace.duration as f64
+    }
+}
+
+// sent by tail_gen_system for every node it adds to a player's tail
+struct TailNodePushed {
+    #[allow(dead_code)]
+    player: Entity,
+}
+
+struct CrossedTailEvent {
+    crosser: Entity,
+    owner: Entity,
+}
+
+// sent by tail_system for every mesh it rebuilt; a system added after tail_system
+// can change the mesh through ResMut<Assets<Mesh>> before it is drawn
+struct TailRemeshed {
+    #[allow(dead_code)]
+    entity: Entity,
+}
+
+struct PaletteSwapEvent {
+    stops: palette::PaletteStops,
+}
+
+// palette to swap to once the tail exists, e.g. from --palette-image
+struct StartupPalette(Option<palette::PaletteStops>);
+
+const IMAGE_PALETTE_SAMPLES: usize = 16;
+
+struct ConsumeTailEvent {
+    player: Entity,
+    count: usize,
+}
+
+const BOOST_COST: usize = 8;
+
+#[derive(Default)]
+struct State {
+    cursor_moved_event_reader: EventReader<CursorMoved>,
+}
+
+// where world (0, 0) sits on screen, in logical pixels from the window center; setup
+// moves the camera by the same amount so the player stays under the cursor
+#[derive(Default)]
+struct WorldOrigin(Vec2);
+
+// K hides and locks the cursor, the player then follows relative motion and can
+// keep going past the window edge
+struct PointerLock {
+    locked: bool,
+    // world units per unit of MouseMotion
+    sensitivity: f32,
+}
+
+impl Default for PointerLock {
+    fn default() -> Self {
+        Self {
+            locked: false,
+            sensitivity: 1.,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PointerLockState {
+    mouse_motion_event_reader: EventReader<MouseMotion>,
+}
+
+fn pointer_lock_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut lock: ResMut<PointerLock>,
+    mut windows: ResMut<Windows>,
+    mut mouse_pos: ResMut<MousePos>,
+    mut state: Local<PointerLockState>,
+    mouse_motion_events: Res<Events<MouseMotion>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::K) {
+        lock.locked = !lock.locked;
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_cursor_lock_mode(lock.locked);
+            window.set_cursor_visibility(!lock.locked);
+        }
+    }
+    for event in state.mouse_motion_event_reader.iter(&mouse_motion_events) {
+        if lock.locked {
+            // motion is in screen space, y grows downward
+            mouse_pos.0 += Vec2::new(event.delta.x, -event.delta.y) * lock.sensitivity;
+        }
+    }
+}
+
+fn mouse_movement_updating_system(
+    mut mouse_pos: ResMut<MousePos>,
+    origin: Res<WorldOrigin>,
+    lock: Res<PointerLock>,
+    windows: Res<Windows>,
+    mut state: Local<State>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+) {
+    for event in state.cursor_moved_event_reader.iter(&cursor_moved_events) {
+        if lock.locked {
+            continue;
+        }
+        // the cursor may be over any window, map it with that window's size
+        let window = match windows.get(event.id) {
+            Some(window) => window,
+            None => continue,
+        };
+        let physical_size = Vec2::new(
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        );
+        mouse_pos.0 = cursor_to_world(
+            event.position,
+            physical_size,
+            window.scale_factor() as f32,
+            origin.0,
+        );
+    }
+}
+
+// CursorMoved is in logical pixels, already divided by the scale factor, so the window
+// is centered with its logical size too; that's also what the 2d camera projection is
+// built from, one world unit per logical pixel
+fn cursor_to_world(position: Vec2, physical_size: Vec2, scale_factor: f32, origin: Vec2) -> Vec2 {
+    let logical_size = physical_size / scale_factor.max(f32::EPSILON);
+    position - logical_size / 2. - origin
+}
+
+#[derive(Default)]
+struct MouseVelocityState {
+    last_pos: Vec2,
+}
+
+fn mouse_velocity_system(
+    time: Res<Time>,
+    mouse_pos: Res<MousePos>,
+    mut state: Local<MouseVelocityState>,
+    mut velocity: ResMut<MouseVelocity>,
+) {
+    let dt = time.delta_seconds();
+    if dt > 0. {
+        velocity.0 = (mouse_pos.0 - state.last_pos) / dt;
+    }
+    state.last_pos = mouse_pos.0;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TailNode {
+    pos: Vec2,
+    velocity: Vec2,
+    width: f32,
+    // seconds since the node was pushed, older saves don't have it
+    #[serde(default)]
+    age: f32,
+    // the player teleported here, no ribbon between this node and the older one
+    #[serde(default)]
+    gap: bool,
+}
+
+impl Default for TailNode {
+    fn default() -> Self {
+        Self {
+            pos: Vec2::zero(),
+            velocity: Vec2::zero(),
+            width: SIZE,
+            age: 0.,
+            gap: false,
+        }
+    }
+}
+
+// a jump between two tail ticks longer than this breaks the ribbon instead of
+// streaking across, a fast mouse flick stays well under it
+const TELEPORT_DISTANCE: f32 = 400.;
+
+fn tail_gaps(tail: &[TailNode]) -> Vec<bool> {
+    tail.iter().map(|node| node.gap).collect()
+}
+
+// reshapes speed / max_speed, already clamped to [0, 1], before it's used
+#[derive(Clone, Copy)]
+enum ResponseCurve {
+    Linear,
+    // spreads the slow end out
+    Log,
+    // spreads the fast end out
+    Exp,
+    // should keep 0 and 1 where they are
+    Custom(fn(f32) -> f32),
+}
+
+// how hard Log and Exp bend
+const RESPONSE_CURVE_STRENGTH: f32 = 9.;
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3. - 2. * t)
+}
+
+impl ResponseCurve {
+    // the names `--width-curve` and `--color-curve` take
+    fn from_arg(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(ResponseCurve::Linear),
+            "log" => Some(ResponseCurve::Log),
+            "exp" => Some(ResponseCurve::Exp),
+            "smoothstep" => Some(ResponseCurve::Custom(smoothstep)),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, t: f32) -> f32 {
+        let k = RESPONSE_CURVE_STRENGTH;
+        match self {
+            ResponseCurve::Linear => t,
+            ResponseCurve::Log => (1. + k * t).ln() / (1. + k).ln(),
+            ResponseCurve::Exp => ((k + 1.).powf(t) - 1.) / k,
+            ResponseCurve::Custom(f) => f(t).clamp(0., 1.),
+        }
+    }
+}
+
+struct SpeedWidth {
+    enabled: bool,
+    min_width: f32,
+    max_width: f32,
+    // distance per tail tick that maps to max_width
+    max_speed: f32,
+    width_curve: ResponseCurve,
+    // for TailColorSource::Speed, which shares max_speed
+    color_curve: ResponseCurve,
+}
+
+impl Default for SpeedWidth {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_width: SIZE / 5.,
+            max_width: SIZE,
+            max_speed: 30.,
+            width_curve: ResponseCurve::Linear,
+            color_curve: ResponseCurve::Linear,
+        }
+    }
+}
+
+impl SpeedWidth {
+    fn width(&self, speed: f32) -> f32 {
+        if !self.enabled {
+            return SIZE;
+        }
+        if self.max_speed <= 0. {
+            return self.max_width;
+        }
+        let t = self
+            .width_curve
+            .apply((speed / self.max_speed).clamp(0.0, 1.0));
+        self.min_width + (self.max_width - self.min_width) * t
+    }
+}
+
+// what Vertex_X, and so the rainbow, follows on the main tail
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TailColorSource {
+    // bands across the width of the ribbon
+    Width,
+    // slow nodes take the first color, SpeedWidth::max_speed and up the last
+    Speed,
+    // heading of each node, right is the first color and it turns counterclockwise
+    Direction,
+    // where on the arena the node was laid, left edge first color, right edge last
+    Position,
+}
+
+impl TailColorSource {
+    fn node_colors(
+        &self,
+        tail: &[TailNode],
+        speed_width: &SpeedWidth,
+        arena: &Arena,
+    ) -> Option<Vec<f32>> {
+        match self {
+            TailColorSource::Width => None,
+            TailColorSource::Speed => Some(
+                tail.iter()
+                    .map(|node| {
+                        let t = node.velocity.length() / speed_width.max_speed.max(f32::EPSILON);
+                        speed_width.color_curve.apply(t.clamp(0., 1.))
+                    })
+                    .collect(),
+            ),
+            TailColorSource::Direction => Some(
+                tail.iter()
+                    .map(|node| {
+                        let angle = node.velocity.y.atan2(node.velocity.x);
+                        (angle / std::f32::consts::TAU).rem_euclid(1.)
+                    })
+                    .collect(),
+            ),
+            TailColorSource::Position => {
+                let width = (arena.max.x - arena.min.x).max(f32::EPSILON);
+                Some(
+                    tail.iter()
+                        .map(|node| ((node.pos.x - arena.min.x) / width).clamp(0., 1.))
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+fn tail_color_source_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut source: ResMut<TailColorSource>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::O) {
+        return;
+    }
+    *source = match *source {
+        TailColorSource::Width => TailColorSource::Speed,
+        TailColorSource::Speed => TailColorSource::Direction,
+        TailColorSource::Direction => TailColorSource::Position,
+        TailColorSource::Position => TailColorSource::Width,
+    };
+    info!("tail color source: {:?}", *source);
+}
+
+// fed by the consumer's own audio analysis, 0 leaves the tail untouched
+struct BeatStrength(f32);
+
+struct BeatResponse {
+    width_gain: f32,
+    intensity_gain: f32,
+}
+
+impl Default for BeatResponse {
+    fn default() -> Self {
+        Self {
+            width_gain: 0.5,
+            intensity_gain: 0.5,
+        }
+    }
+}
+
+// pixels a node of width SIZE takes up on screen whatever the camera scale, others
+// keep their width relative to it; 0 leaves widths in world units
+#[derive(Default)]
+struct ScreenSpaceWidth(f32);
+
+impl ScreenSpaceWidth {
+    fn apply(&self, nodes: &mut [TailNode], zoom: f32) {
+        if self.0 <= 0. {
+            return;
+        }
+        let scale = self.0 * zoom / SIZE;
+        for node in nodes.iter_mut() {
+            node.width *= scale;
+        }
+    }
+}
+
+// world units a node may be off the decimated path, 0 keeps every node
+#[derive(Default)]
+struct TailDecimation {
+    tolerance: f32,
+}
+
+// radians the ribbon may turn at one node, sharper corners get rounded off;
+// pi and up leaves every corner alone
+struct CurvatureLimit {
+    max_angle: f32,
+}
+
+impl Default for CurvatureLimit {
+    fn default() -> Self {
+        Self {
+            max_angle: std::f32::consts::PI,
+        }
+    }
+}
+
+struct TailSmoothing {
+    window: usize,
+    // nodes whose directions are averaged into each normal, 1 keeps the raw velocity
+    normal_window: usize,
+}
+
+impl TailSmoothing {
+    fn smooth(&self, player: &Player) -> Vec<TailNode> {
+        let mut nodes = player.smoothed_tail(self.window);
+        smooth_normals(&mut nodes, self.normal_window);
+        nodes
+    }
+}
+
+// only the velocity directions change, make_sub_tail turns them into the normals and
+// the speed based width and color still see the original lengths
+fn smooth_normals(nodes: &mut [TailNode], window: usize) {
+    let half = window / 2;
+    if half == 0 || nodes.is_empty() {
+        return;
+    }
+    let directions: Vec<Vec2> = nodes
+        .iter()
+        .map(|node| safe_normalize(node.velocity, Vec2::zero()))
+        .collect();
+    let len = nodes.len();
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let from = i.saturating_sub(half);
+        let to = (i + half).min(len - 1);
+        let sum = directions[from..=to]
+            .iter()
+            .fold(Vec2::zero(), |sum, direction| sum + *direction);
+        // opposite directions cancel out, keep the node's own then
+        node.velocity = safe_normalize(sum, directions[i]) * node.velocity.length();
+    }
+}
+
+// how fast node widths move toward their target, 0 snaps instantly
+struct WidthEasing {
+    rate: f32,
+}
+
+const TAIL_LEN: usize = 32;
+// keeps the ribbon's vertex count well inside u16 indices
+const MIN_TAIL_LEN: usize = 2;
+const MAX_TAIL_LEN: usize = 256;
+const TAIL_LEN_STEP: usize = 4;
+
+fn centerline(tail: &[TailNode]) -> Vec<Vec2> {
+    tail.iter().map(|node| node.pos).collect()
+}
+
+pub struct Player {
+    size: f32,
+    // newest node first, only nodes that were actually pushed, never longer than tail_len
+    pub tail: Vec<TailNode>,
+    tail_len: usize,
+    node_width: NodeWidth,
+    // where the newest node was pushed, the spawn position before that
+    position: Vec2,
+}
+
+// where push_tail_node takes a new node's width from
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NodeWidth {
+    // the head's, SIZE for the first node, then tail_width_system eases it by speed
+    Derived,
+    // tail_width_system leaves these nodes alone, game logic may still set
+    // TailNode::width afterwards
+    Fixed(f32),
+}
+
+struct Tail {
+    player: Option<Entity>,
+}
+
+// what orphan_tail_system does with a tail whose player is gone, `--orphan-tails`
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum OrphanTailPolicy {
+    // empties the mesh, the entity stays
+    Hide,
+    // keeps the last mesh as it was
+    Freeze,
+    Despawn,
+}
+
+struct Extrude3d {
+    enabled: bool,
+    depth: f32,
+}
+
+impl Default for Extrude3d {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth: 40.,
+        }
+    }
+}
+
+// tail built with make_tail_mesh_3d
+struct Extruded;
+
+// drawn behind the tail of the same player, in TailStroke::color
+struct Outline;
+
+// D flips between the two at runtime, Extrude3d::enabled only picks the first one
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum RenderDimension {
+    Flat,
+    Extruded,
+}
+
+struct TailPipelines {
+    flat: Handle<PipelineDescriptor>,
+    extruded: Handle<PipelineDescriptor>,
+    // flat with PACKED_VERTEX_SHADER, vertex_precision_system picks between the two
+    packed: Handle<PipelineDescriptor>,
+}
+
+impl TailPipelines {
+    fn get(&self, dimension: RenderDimension) -> &Handle<PipelineDescriptor> {
+        match dimension {
+            RenderDimension::Flat => &self.flat,
+            RenderDimension::Extruded => &self.extruded,
+        }
+    }
+}
+
+// one world unit is about one pixel at z = 0 for a 720 high window
+const DEFAULT_CAMERA_DISTANCE: f32 = 869.0;
+
+// how far back the 3d camera sits so that cursor_to_world stays right for the flat ribbon
+fn camera_distance(windows: &Windows) -> f32 {
+    match windows.get_primary() {
+        Some(window) => {
+            let fov = PerspectiveProjection::default().fov;
+            window.height() / 2.0 / (fov / 2.0).tan()
+        }
+        None => DEFAULT_CAMERA_DISTANCE,
+    }
+}
+
+fn spawn_main_camera(
+    commands: &mut Commands,
+    dimension: RenderDimension,
+    eye: Vec2,
+    distance: f32,
+) {
+    match dimension {
+        RenderDimension::Extruded => {
+            // straight on, so the 2d and 3d views line up
+            let eye = eye.extend(0.0);
+            commands
+                .spawn(Camera3dBundle {
+                    transform: Transform::from_translation(eye + Vec3::new(0.0, 0.0, distance))
+                        .looking_at(eye, Vec3::unit_y()),
+                    ..Default::default()
+                })
+                .with(MainCamera);
+        }
+        RenderDimension::Flat => {
+            let mut camera = Camera2dBundle::default();
+            camera.transform.translation.x = eye.x;
+            camera.transform.translation.y = eye.y;
+            commands.spawn(camera).with(MainCamera);
+        }
+    }
+}
+
+fn render_dimension_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    precision: Res<AttributePrecision>,
+    mut dimension: ResMut<RenderDimension>,
+) {
+    if keyboard_input.just_pressed(KeyCode::D) {
+        *dimension = match *dimension {
+            RenderDimension::Flat => RenderDimension::Extruded,
+            RenderDimension::Extruded => RenderDimension::Flat,
+        };
+        info!("render dimension: {:?}", *dimension);
+        if *dimension == RenderDimension::Extruded && *precision == AttributePrecision::Packed {
+            info!("the 3d tail has no packed vertex shader, it keeps full precision attributes");
+        }
+    }
+}
+
+// the tail nodes stay on the player, so swapping the pipeline is enough for tail_system
+// to rebuild the same shape with the other mesh builder next frame
+fn render_dimension_system(
+    commands: &mut Commands,
+    dimension: ChangedRes<RenderDimension>,
+    tail_pipelines: Res<TailPipelines>,
+    windows: Res<Windows>,
+    cameras: Query<(Entity, &Transform), With<MainCamera>>,
+    mut tails: Query<
+        (Entity, &mut RenderPipelines, Option<&Extruded>),
+        (With<Tail>, Without<Outline>),
+    >,
+) {
+    let extruded = *dimension == RenderDimension::Extruded;
+    let mut changed = false;
+    for (entity, mut render_pipelines, current) in tails.iter_mut() {
+        if current.is_some() == extruded {
+            continue;
+        }
+        changed = true;
+        *render_pipelines = RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+            tail_pipelines.get(*dimension).clone(),
+        )]);
+        if extruded {
+            commands.insert_one(entity, Extruded);
+        } else {
+            commands.remove_one::<Extruded>(entity);
+        }
+    }
+    if !changed {
+        return;
+    }
+    // keep looking at the same spot, so follow and shake carry on from there
+    let mut eye = None;
+    for (entity, trans) in cameras.iter() {
+        eye = Some(Vec2::new(trans.translation.x, trans.translation.y));
+        commands.despawn(entity);
+    }
+    if let Some(eye) = eye {
+        spawn_main_camera(commands, *dimension, eye, camera_distance(&windows));
+    }
+}
+
+struct TailStroke {
+    width: f32,
+    color: Color,
+}
+
+impl Default for TailStroke {
+    fn default() -> Self {
+        Self {
+            width: 0.,
+            color: Color::BLACK,
+        }
+    }
+}
+
+// scripted movement, the mouse is ignored while this is present
+struct MoveTarget(Vec2);
+
+// scripted movement along a cubic bezier, the mouse is ignored while this is present
+struct BezierPath {
+    p0: Vec2,
+    p1: Vec2,
+    p2: Vec2,
+    p3: Vec2,
+    duration: f32,
+    elapsed: f32,
+    looping: bool,
+}
+
+impl BezierPath {
+    fn point(&self, t: f32) -> Vec2 {
+        let u = 1. - t;
+        self.p0 * (u * u * u)
+            + self.p1 * (3. * u * u * t)
+            + self.p2 * (3. * u * t * t)
+            + self.p3 * (t * t * t)
+    }
+}
+
+struct BezierPlayback {
+    duration: f32,
+    looping: bool,
+}
+
+impl Default for BezierPlayback {
+    fn default() -> Self {
+        Self {
+            duration: 3.,
+            looping: true,
+        }
+    }
+}
+
+// players that are neither replaying nor on a scripted path follow their InputSource
+type InputDriven = (
+    With<Player>,
+    Without<ReplayTail>,
+    Without<MoveTarget>,
+    Without<BezierPath>,
+    Without<Rewinding>,
+);
+
+#[derive(Clone, Copy)]
+struct MoveKeys {
+    up: KeyCode,
+    down: KeyCode,
+    left: KeyCode,
+    right: KeyCode,
+}
+
+impl Default for MoveKeys {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::Up,
+            down: KeyCode::Down,
+            left: KeyCode::Left,
+            right: KeyCode::Right,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum InputSource {
+    Mouse,
+    Keyboard { keys: MoveKeys },
+    Gamepad { id: Gamepad },
+}
+
+// pixels per second for keyboard and gamepad players at full deflection, and toward
+// a MoveTarget; the mouse player just goes where the cursor is
+struct Speed(f32);
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self(600.)
+    }
+}
+
+impl Speed {
+    fn get(&self) -> f32 {
+        self.0
+    }
+
+    fn set(&mut self, speed: f32) {
+        if speed.is_finite() {
+            self.0 = speed.max(0.);
+        }
+    }
+}
+
+// Space also multiplies Speed by factor for duration seconds, 1 leaves it alone
+struct SpeedBoost {
+    factor: f32,
+    duration: f32,
+}
+
+impl Default for SpeedBoost {
+    fn default() -> Self {
+        Self {
+            factor: 1.,
+            duration: 0.5,
+        }
+    }
+}
+
+// put back to base once remaining runs out
+struct Boosted {
+    base: f32,
+    remaining: f32,
+}
+
+struct TargetMovement {
+    clear_on_reach: bool,
+}
+
+impl Default for TargetMovement {
+    fn default() -> Self {
+        Self {
+            clear_on_reach: true,
+        }
+    }
+}
+
+struct ReachedTargetEvent {
+    player: Entity,
+    target: Vec2,
+}
+
+struct TrajectoryPrediction {
+    enabled: bool,
+    // seconds of travel at the current velocity the line covers
+    horizon: f32,
+}
+
+impl Default for TrajectoryPrediction {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            horizon: 0.25,
+        }
+    }
+}
+
+const PREDICTION_SEGMENTS: usize = 8;
+
+struct PredictionLine {
+    player: Option<Entity>,
+}
+
+// debug view of the area the ribbon covers, toggled with H
+#[derive(Default)]
+struct HullDebug {
+    enabled: bool,
+}
+
+struct HullLine {
+    player: Entity,
+}
+
+struct GhostTail {
+    enabled: bool,
+    alpha: f32,
+}
+
+impl Default for GhostTail {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            alpha: 0.25,
+        }
+    }
+}
+
+// holds the mesh the tail drew last frame, the two swap handles before every rebuild
+struct Ghost {
+    tail: Entity,
+}
+
+// a frozen copy of a tail, left behind with the T key
+struct Stamp;
+
+// stamps with the same geometry point at one mesh, so stamping an idle tail over and
+// over costs one vertex and index buffer instead of one per stamp; keyed by the
+// nodes themselves, so a hash collision still has to compare equal
+#[derive(Default)]
+struct StampMeshCache {
+    meshes: HashMap<Vec<StampNodeKey>, Handle<Mesh>>,
+}
+
+// everything make_tail_mesh reads of a node, the age only feeds the decay
+type StampNodeKey = [u32; 6];
+
+fn stamp_key(tail: &[TailNode]) -> Vec<StampNodeKey> {
+    tail.iter()
+        .map(|node| {
+            [
+                node.pos.x.to_bits(),
+                node.pos.y.to_bits(),
+                node.velocity.x.to_bits(),
+                node.velocity.y.to_bits(),
+                node.width.to_bits(),
+                node.gap as u32,
+            ]
+        })
+        .collect()
+}
+
+const SAVED_TAIL_PATH: &str = "tail.ron";
+const SAVED_STATE_PATH: &str = "state.ron";
+
+struct ReplaySpeed(f32);
+
+struct ReplayTail {
+    // oldest node first, in the order they get pushed
+    nodes: Vec<TailNode>,
+    next: usize,
+    progress: f32,
+}
+
+impl ReplayTail {
+    fn new(saved: &[TailNode]) -> Self {
+        Self {
+            nodes: saved.iter().rev().copied().collect(),
+            next: 0,
+            progress: 0.,
+        }
+    }
+
+    // the tail is drawn again from nothing, at the saved length, so none of the old
+    // nodes are left behind and none of the saved ones get truncated
+    fn start(player: &mut Player, saved: &[TailNode]) -> Self {
+        player.tail.clear();
+        player.set_tail_len(saved.len());
+        Self::new(saved)
+    }
+}
+
+impl Player {
+    // a wavy tail of len nodes, what the snapshot check and the mesh benches build
+    pub fn full(len: usize) -> Self {
+        let mut player = Self {
+            size: SIZE,
+            tail: vec![],
+            tail_len: len,
+            node_width: NodeWidth::Derived,
+            position: Vec2::zero(),
+        };
+        for i in 0..len {
+            player.push_tail_node(Vec2::new(i as f32 * 5., (i as f32 * 0.1).sin() * 50.));
+        }
+        player
+    }
+
+    // false when pos was rejected
+    pub fn push_tail_node(&mut self, pos: Vec2) -> bool {
+        // one bad position would poison the normals of every segment it touches
+        if !pos.is_finite() {
+            return false;
+        }
+        let head = self.tail.first().copied().unwrap_or(TailNode {
+            pos,
+            ..Default::default()
+        });
+        let gap = !self.tail.is_empty() && pos.distance(head.pos) > TELEPORT_DISTANCE;
+        let mut velocity = pos - head.pos;
+        // the jump isn't movement, so it shouldn't count toward speed or direction
+        if gap || pos.distance_squared(head.pos) < 2. {
+            velocity = head.velocity;
+        }
+        let width = match self.node_width {
+            NodeWidth::Derived => head.width,
+            NodeWidth::Fixed(width) => width,
+        };
+        self.push_node(TailNode {
+            pos,
+            velocity,
+            width,
+            age: 0.,
+            gap,
+        });
+        true
+    }
+
+    // newest node first; nodes past the end of widths keep theirs, and the whole tail
+    // stops following tail_width_system so the profile sticks
+    #[allow(dead_code)]
+    pub fn set_node_widths(&mut self, widths: &[f32]) {
+        for (node, width) in self.tail.iter_mut().zip(widths) {
+            node.width = width.max(0.);
+        }
+        if let NodeWidth::Derived = self.node_width {
+            self.node_width = NodeWidth::Fixed(self.tail.first().map_or(SIZE, |head| head.width));
+        }
+    }
+
+    pub fn push_node(&mut self, node: TailNode) {
+        self.position = node.pos;
+        self.tail.insert(0, node);
+        self.tail.truncate(self.tail_len);
+    }
+
+    pub fn set_tail_len(&mut self, len: usize) {
+        self.tail_len = len.clamp(MIN_TAIL_LEN, MAX_TAIL_LEN);
+        self.tail.truncate(self.tail_len);
+        self.tail
+            .reserve_exact(self.tail_len.saturating_sub(self.tail.len()));
+    }
+
+    pub fn centerline(&self) -> Vec<Vec2> {
+        centerline(&self.tail)
+    }
+
+    // each drawn segment, head end first, with the width at its head end; gaps are
+    // left out like make_tail_mesh leaves them, so a tail without any has len - 1
+    pub fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2, f32)> + '_ {
+        self.tail
+            .windows(2)
+            .filter(|pair| !pair[0].gap)
+            .map(|pair| (pair[0].pos, pair[1].pos, pair[0].width))
+    }
+
+    // arc length of the centerline from head to end
+    pub fn tail_length(&self) -> f32 {
+        self.tail
+            .windows(2)
+            .map(|pair| pair[0].pos.distance(pair[1].pos))
+            .sum()
+    }
+
+    // the node `distance` along the centerline from the head, position interpolated
+    // between the two nodes around it, None past the end
+    pub fn sample_tail(&self, distance: f32) -> Option<TailNode> {
+        let mut walked = 0.;
+        for pair in self.tail.windows(2) {
+            let segment = pair[0].pos.distance(pair[1].pos);
+            if walked + segment >= distance && segment > 0. {
+                let t = (distance - walked) / segment;
+                return Some(TailNode {
+                    pos: pair[0].pos.lerp(pair[1].pos, t),
+                    ..pair[0]
+                });
+            }
+            walked += segment;
+        }
+        None
+    }
+
+    // min and max corner of the centerline, the ribbon reaches up to a node width further
+    pub fn tail_aabb(&self) -> Option<(Vec2, Vec2)> {
+        let first = self.tail.first()?.pos;
+        Some(self.tail.iter().fold((first, first), |(min, max), node| {
+            (min.min(node.pos), max.max(node.pos))
+        }))
+    }
+
+    // mean of the node positions; the tail only holds pushed nodes, so there are no
+    // unfilled ones to skip, an empty tail is centered on the player
+    pub fn tail_centroid(&self) -> Vec2 {
+        if self.tail.is_empty() {
+            return self.position;
+        }
+        let sum = self
+            .tail
+            .iter()
+            .fold(Vec2::zero(), |sum, node| sum + node.pos);
+        sum / self.tail.len() as f32
+    }
+
+    // moving average of each node's position over `window` nodes centered on it,
+    // a window of 1 gives the raw tail back
+    pub fn smoothed_tail(&self, window: usize) -> Vec<TailNode> {
+        let half = window / 2;
+        let len = self.tail.len();
+        (0..len)
+            .map(|i| {
+                // never averaged across a gap, that would pull both ends toward each other
+                let mut from = i;
+                while from > i.saturating_sub(half) && !self.tail[from - 1].gap {
+                    from -= 1;
+                }
+                let mut to = i;
+                while to < (i + half).min(len - 1) && !self.tail[to].gap {
+                    to += 1;
+                }
+                let sum = self.tail[from..=to]
+                    .iter()
+                    .fold(Vec2::zero(), |sum, node| sum + node.pos);
+                TailNode {
+                    pos: sum / (to - from + 1) as f32,
+                    ..self.tail[i]
+                }
+            })
+            .collect()
+    }
+
+    // the end becomes the head; velocities are redone the way push_tail_node would
+    // have given them with the nodes pushed in the new order
+    pub fn reverse_tail(&mut self) {
+        self.tail.reverse();
+        // a gap belongs to the segment after its node, which is now the segment before
+        let len = self.tail.len();
+        for i in 0..len {
+            self.tail[i].gap = i + 1 < len && self.tail[i + 1].gap;
+        }
+        if let Some(end) = self.tail.last_mut() {
+            end.velocity = -end.velocity;
+        }
+        for i in (0..len.saturating_sub(1)).rev() {
+            let older = self.tail[i + 1];
+            let step = self.tail[i].pos - older.pos;
+            self.tail[i].velocity = if self.tail[i].gap || step.length_squared() < 2. {
+                older.velocity
+            } else {
+                step
+            };
+        }
+    }
+
+    pub fn consume_tail(&mut self, n: usize) {
+        let len = self.tail.len().saturating_sub(n);
+        self.tail.truncate(len);
+    }
+
+    #[allow(dead_code)]
+    pub fn make_debug_tail(&mut self, pos: Vec2) {
+        let scale = 200.;
+        if self.tail.len() < 4 {
+            self.tail.resize(4, TailNode::default());
+        }
+        self.tail[0] = TailNode {
+            pos,
+            velocity: Vec2::new(scale, 0.),
+            ..Default::default()
+        };
+        self.tail[1] = TailNode {
+            pos: pos + Vec2::new(-scale, 0.),
+            velocity: Vec2::new(1., -1.),
+            ..Default::default()
+        };
+        self.tail[2] = TailNode {
+            pos: pos + Vec2::new(-2. * scale, 1. * scale),
+            velocity: Vec2::new(-1.0, 0.),
+            ..Default::default()
+        };
+        self.tail[3] = TailNode {
+            pos: pos + Vec2::new(-2. * scale, 2. * scale),
+            velocity: Vec2::new(-1.0, 0.),
+            ..Default::default()
+        };
+    }
+}
+
+// z of the player and of the main tail, the outline, ghost and debug lines keep their
+// offsets from the tail; bevy 0.4 has no render layers, so z is the only ordering
+#[derive(Default)]
+struct TailLayers {
+    player: f32,
+    tail: f32,
+    // higher draws over the tails of players with a lower one
+    render_priority: i32,
+}
+
+// more than the spread of one player's tail, outline and debug lines
+const TAIL_PRIORITY_STEP: f32 = 2.;
+// equal priorities stack in spawn order, MaxPlayers of these stay under a step
+const TAIL_ORDER_STEP: f32 = 0.01;
+
+// added to both TailLayers z for the player spawned `order`th
+fn render_priority_z(render_priority: i32, order: usize) -> f32 {
+    render_priority as f32 * TAIL_PRIORITY_STEP + order as f32 * TAIL_ORDER_STEP
+}
+
+// offsets from the z setup gives the main tail from TailLayers, for everything
+// drawn with it and the copies made of it later, so all of one player stays inside
+// a TAIL_PRIORITY_STEP and moves with the layer
+const HULL_Z: f32 = 0.2;
+const GHOST_Z: f32 = -0.25;
+const BRANCH_Z: f32 = -0.5;
+const OUTLINE_Z: f32 = -1.0;
+const STAMP_Z: f32 = -1.5;
+
+// asset path of a mesh drawn instead of the quad, e.g. "ship.gltf#Mesh0/Primitive0"
+#[derive(Default)]
+struct PlayerMesh(Option<String>);
+
+// players, and main tails, allowed at once
+struct MaxPlayers(usize);
+
+// spawn_player and attach_tail count ahead, player_count_system recounts after despawns
+#[derive(Default)]
+struct PlayerCount {
+    players: usize,
+    tails: usize,
+}
+
+#[derive(Debug)]
+enum SpawnError {
+    MaxPlayers(usize),
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpawnError::MaxPlayers(max) => write!(f, "already at the maximum of {} players", max),
+        }
+    }
+}
+
+fn spawn_player(
+    commands: &mut Commands,
+    count: &mut PlayerCount,
+    max: &MaxPlayers,
+    player: Player,
+    mesh: Option<Handle<Mesh>>,
+    meshes: &mut Assets<Mesh>,
+    material: Handle<ColorMaterial>,
+    color: PlayerColor,
+    position: Vec3,
+) -> Result<Entity, SpawnError> {
+    if count.players >= max.0 {
+        return Err(SpawnError::MaxPlayers(max.0));
+    }
+    count.players += 1;
+    // the tail only follows the transform, so any mesh works here
+    let mesh = mesh.unwrap_or_else(|| meshes.add(make_player_mesh(player.size)));
+    let entity = commands
+        .spawn(SpriteBundle {
+            mesh,
+            material,
+            sprite: Sprite {
+                size: Vec2::new(1.0, 1.0),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(position),
+            ..Default::default()
+        })
+        .with(player)
+        // starting both at the spawn point keeps the first node and speed from
+        // reaching back to the origin
+        .with(SimPosition {
+            previous: position.truncate(),
+            current: position.truncate(),
+        })
+        .with(Velocity {
+            last_position: position.truncate(),
+            ..Default::default()
+        })
+        .with(Idle::default())
+        .with(InputSource::Mouse)
+        .with(Speed::default())
+        .with(color)
+        .current_entity()
+        .unwrap();
+    Ok(entity)
+}
+
+// the main ribbon of `player`, outlines and ghosts are extra and don't count; the
+// palette, alpha and decay systems write into the material asset, so every tail
+// gets its own instead of sharing a handle
+fn attach_tail(
+    commands: &mut Commands,
+    count: &mut PlayerCount,
+    max: &MaxPlayers,
+    player: Entity,
+    bundle: MeshBundle,
+    material: MyMaterialWithVertexColorSupport,
+    materials: &mut Assets<MyMaterialWithVertexColorSupport>,
+) -> Result<Entity, SpawnError> {
+    if count.tails >= max.0 {
+        return Err(SpawnError::MaxPlayers(max.0));
+    }
+    count.tails += 1;
+    let entity = commands
+        .spawn(bundle)
+        .with(materials.add(material))
+        .with(Tail {
+            player: Some(player),
+        })
+        .current_entity()
+        .unwrap();
+    Ok(entity)
+}
+
+fn player_count_system(
+    mut count: ResMut<PlayerCount>,
+    players: Query<(), With<Player>>,
+    tails: Query<(), (With<Tail>, Without<Outline>)>,
+) {
+    count.players = players.iter().count();
+    count.tails = tails.iter().count();
+}
+
+fn setup(
+    commands: &mut Commands,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    mut render_graph: ResMut<RenderGraph>,
+    stroke: Res<TailStroke>,
+    extrude: Res<Extrude3d>,
+    tail_shaders: Res<TailShaders>,
+    length_cycles: Res<LengthCycles>,
+    asset_server: Res<AssetServer>,
+    hot_reload: Res<ShaderHotReload>,
+    // grouped, a system takes at most 16 parameters
+    (max_players, mut player_count): (Res<MaxPlayers>, ResMut<PlayerCount>),
+    (player_mesh, player_shape): (Res<PlayerMesh>, Res<PlayerShape>),
+    (origin, layers, initial_position, label_config): (
+        Res<WorldOrigin>,
+        Res<TailLayers>,
+        Res<InitialPosition>,
+        Res<labels::LabelConfig>,
+    ),
+) {
+    let priority_z = render_priority_z(layers.render_priority, player_count.players);
+    let at_tail =
+        |z: f32| Transform::from_translation(Vec3::new(0.0, 0.0, layers.tail + priority_z + z));
+    let player_color = PlayerColor(Color::rgb(1.0, 1.0, 1.0));
+    let player_material = color_materials.add(player_color.0.into());
+    let dimension = if extrude.enabled {
+        RenderDimension::Extruded
+    } else {
+        RenderDimension::Flat
+    };
+    // the window isn't up yet, so the 3d camera starts at the distance for the default size
+    spawn_main_camera(commands, dimension, -origin.0, DEFAULT_CAMERA_DISTANCE);
+    commands.insert_resource(dimension);
+    commands.spawn(CameraUiBundle::default());
+    let player = Player {
+        size: SIZE,
+        tail: Vec::with_capacity(TAIL_LEN),
+        tail_len: TAIL_LEN,
+        node_width: NodeWidth::Derived,
+        position: initial_position.0,
+    };
+
+    if hot_reload.0 {
+        if let Err(err) = asset_server.watch_for_changes() {
+            println!("failed to watch shaders for changes: {:?}", err);
+        }
+    }
+    let stages = ShaderStages {
+        vertex: tail_shader_stage(
+            ShaderStage::Vertex,
+            tail_shaders.vertex.as_deref(),
+            TAIL_VERTEX_SHADER_FILE,
+            VERTEX_SHADER,
+            &mut shaders,
+            &asset_server,
+        ),
+        fragment: Some(tail_shader_stage(
+            ShaderStage::Fragment,
+            tail_shaders.fragment.as_deref(),
+            TAIL_FRAGMENT_SHADER_FILE,
+            FRAGMENT_SHADER,
+            &mut shaders,
+            &asset_server,
+        )),
+    };
+    let fragment = stages.fragment.clone();
+    let pipeline_handle = add_rainbow_pipeline(stages, &mut pipelines);
+
+    let custom_mesh = match player_mesh.0.as_deref() {
+        Some(path) => Some(asset_server.load(path)),
+        None if player_shape.corner_radius > 0. => Some(meshes.add(make_rounded_rect_mesh(
+            player.size,
+            player_shape.corner_radius,
+            player_shape.segments,
+        ))),
+        None => None,
+    };
+    let player_entity = match spawn_player(
+        commands,
+        &mut player_count,
+        &max_players,
+        player,
+        custom_mesh,
+        &mut meshes,
+        player_material,
+        player_color,
+        initial_position.0.extend(layers.player + priority_z),
+    ) {
+        Ok(entity) => entity,
+        Err(err) => {
+            println!("failed to spawn player: {}", err);
+            return;
+        }
+    };
+    commands.insert_one(
+        player_entity,
+        labels::PlayerName(label_config.player_name.clone()),
+    );
+
+    render_graph.add_system_node(
+        "my_material_with_vertex_color_support",
+        AssetRenderResourcesNode::<MyMaterialWithVertexColorSupport>::new(true),
+    );
+
+    render_graph
+        .add_node_edge(
+            "my_material_with_vertex_color_support",
+            base::node::MAIN_PASS,
+        )
+        .unwrap();
+
+    commands
+        .spawn(MeshBundle {
+            mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                pipeline_handle.clone(),
+            )]),
+            transform: at_tail(0.0),
+            // shown by prediction_system once there's a line to draw
+            visible: Visible {
+                is_visible: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(materials.add(MyMaterialWithVertexColorSupport {
+            global_alpha: 0.5,
+            ..Default::default()
+        }))
+        .with(PredictionLine {
+            player: Some(player_entity),
+        });
+
+    commands
+        .spawn(MeshBundle {
+            mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                pipeline_handle.clone(),
+            )]),
+            transform: at_tail(HULL_Z),
+            // shown by hull_system while H has it on
+            visible: Visible {
+                is_visible: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(materials.add(MyMaterialWithVertexColorSupport::default()))
+        .with(HullLine {
+            player: player_entity,
+        });
+
+    if stroke.width > 0. {
+        let color = Vec4::new(stroke.color.r(), stroke.color.g(), stroke.color.b(), 1.0);
+        let outline_material = materials.add(MyMaterialWithVertexColorSupport {
+            palette: vec![color; palette::PALETTE_STOPS],
+            ..Default::default()
+        });
+        commands
+            .spawn(MeshBundle {
+                mesh: meshes.add(make_mesh(&[], vec![])),
+                render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                    pipeline_handle.clone(),
+                )]),
+                transform: at_tail(OUTLINE_Z),
+                ..Default::default()
+            })
+            .with(outline_material)
+            .with(Tail {
+                player: Some(player_entity),
+            })
+            .with(Outline);
+    }
+
+    let material = MyMaterialWithVertexColorSupport {
+        length_cycles: length_cycles.0,
+        ..Default::default()
+    };
+
+    let tail_pipelines = TailPipelines {
+        flat: pipeline_handle.clone(),
+        extruded: make_rainbow_pipeline(
+            VERTEX_SHADER_3D,
+            FRAGMENT_SHADER_3D,
+            &mut shaders,
+            &mut pipelines,
+        ),
+        packed: add_rainbow_pipeline(
+            ShaderStages {
+                vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, PACKED_VERTEX_SHADER)),
+                fragment,
+            },
+            &mut pipelines,
+        ),
+    };
+    let bundle = MeshBundle {
+        mesh: meshes.add(make_mesh(&[], vec![])),
+        render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+            tail_pipelines.get(dimension).clone(),
+        )]),
+        transform: at_tail(0.0),
+        ..Default::default()
+    };
+    let tail_entity = attach_tail(
+        commands,
+        &mut player_count,
+        &max_players,
+        player_entity,
+        bundle,
+        material,
+        &mut materials,
+    );
+
+    match tail_entity {
+        Err(err) => println!("failed to attach tail: {}", err),
+        Ok(tail) => {
+            if dimension == RenderDimension::Extruded {
+                commands.insert_one(tail, Extruded);
+            }
+            commands
+                .spawn(MeshBundle {
+                    mesh: meshes.add(make_mesh(&[], vec![])),
+                    render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                        pipeline_handle,
+                    )]),
+                    visible: Visible {
+                        is_visible: false,
+                        ..Default::default()
+                    },
+                    transform: at_tail(GHOST_Z),
+                    ..Default::default()
+                })
+                .with(materials.add(MyMaterialWithVertexColorSupport::default()))
+                .with(Ghost { tail });
+        }
+    }
+    commands.insert_resource(tail_pipelines);
+}
+
+fn move_system(
+    time: Res<Time>,
+    hitch_guard: Res<HitchGuard>,
+    mouse_pos: Res<MousePos>,
+    mouse_velocity: Res<MouseVelocity>,
+    prediction: Res<MousePrediction>,
+    interpolation: Res<RenderInterpolation>,
+    keyboard_input: Res<Input<KeyCode>>,
+    axes: Res<Axis<GamepadAxis>>,
+    dead_zone: Res<DeadZone>,
+    (center, origin): (Res<CenterPlayer>, Res<WorldOrigin>),
+    follow: Res<TailFollow>,
+    mut query: Query<(&mut Transform, &InputSource, &Speed), InputDriven>,
+) {
+    if hitch_guard.is_hitch(&time) || *follow == TailFollow::Mouse {
+        return;
+    }
+    let mouse_target = mouse_pos.0 + mouse_velocity.0 * prediction.time;
+    for (mut trans, source, speed) in query.iter_mut() {
+        let direction = match *source {
+            // the player is always at the screen center, so the cursor's offset from it
+            // is where to head
+            InputSource::Mouse if center.enabled => {
+                let offset = mouse_pos.0 + origin.0;
+                if offset.length() < dead_zone.radius {
+                    continue;
+                }
+                offset / center.steer_radius.max(f32::EPSILON)
+            }
+            // with interpolation the mouse position only advances on tail ticks
+            InputSource::Mouse => {
+                let pos = Vec2::new(trans.translation.x, trans.translation.y);
+                if !interpolation.0 && pos.distance(mouse_target) >= dead_zone.radius {
+                    trans.translation.x = mouse_target.x;
+                    trans.translation.y = mouse_target.y;
+                }
+                continue;
+            }
+            InputSource::Keyboard { keys } => {
+                let axis = |negative, positive| {
+                    keyboard_input.pressed(positive) as i32 as f32
+                        - keyboard_input.pressed(negative) as i32 as f32
+                };
+                Vec2::new(axis(keys.left, keys.right), axis(keys.down, keys.up))
+            }
+            InputSource::Gamepad { id } => {
+                let axis = |axis_type| axes.get(GamepadAxis(id, axis_type)).unwrap_or(0.);
+                Vec2::new(
+                    axis(GamepadAxisType::LeftStickX),
+                    axis(GamepadAxisType::LeftStickY),
+                )
+            }
+        };
+        // diagonals are no faster than straight lines
+        let direction = if direction.length_squared() > 1. {
+            direction.normalize()
+        } else {
+            direction
+        };
+        let step = speed.get() * time.delta_seconds();
+        trans.translation.x += direction.x * step;
+        trans.translation.y += direction.y * step;
+    }
+}
+
+// what tail_gen_system samples; Mouse is the ribbon cursor, the tail trails the
+// cursor itself and the player quad is hidden and left where it is
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum TailFollow {
+    Player,
+    Mouse,
+}
+
+fn tail_follow_system(
+    follow: ChangedRes<TailFollow>,
+    mut players: Query<&mut Visible, With<Player>>,
+) {
+    for mut visible in players.iter_mut() {
+        visible.is_visible = *follow == TailFollow::Player;
+    }
+}
+
+// nodes tail_gen_system may add per player in one frame when a slow frame missed
+// several TailTimer ticks, 1 drops the missed ones
+struct TailPushCap {
+    max_per_frame: usize,
+    // --even-spacing, places each of those nodes where the player was at its tick,
+    // between where the frame started and ended, instead of evenly up to the head
+    interpolate: bool,
+}
+
+impl Default for TailPushCap {
+    fn default() -> Self {
+        Self {
+            max_per_frame: 1,
+            interpolate: false,
+        }
+    }
+}
+
+// enough for 20fps against the default tail tick
+const EVEN_SPACING_MAX_PUSHES: usize = 8;
+
+// mouse moves shorter than radius from the player are ignored, 0 follows every jitter
+#[derive(Default)]
+struct DeadZone {
+    radius: f32,
+}
+
+struct IdleRetract {
+    enabled: bool,
+    // seconds without movement before the tail starts to shrink
+    timeout: f32,
+    // nodes removed from the tail end per second while idle
+    rate: f32,
+}
+
+impl Default for IdleRetract {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: 1.,
+            rate: 30.,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Idle {
+    last_pos: Vec2,
+    time: f32,
+    // fraction of a node still owed to the retraction
+    carry: f32,
+}
+
+// I switches every player between mouse, arrow keys and the first gamepad
+fn input_source_cycle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut InputSource, With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::I) {
+        return;
+    }
+    for mut source in query.iter_mut() {
+        *source = match *source {
+            InputSource::Mouse => InputSource::Keyboard {
+                keys: MoveKeys::default(),
+            },
+            InputSource::Keyboard { .. } => InputSource::Gamepad { id: Gamepad(0) },
+            InputSource::Gamepad { .. } => InputSource::Mouse,
+        };
+    }
+}
+
+#[derive(Default)]
+struct TailGenState {
+    after_hitch: bool,
+    // each player's translation at the end of the last frame
+    last_positions: HashMap<Entity, Vec2>,
+}
+
+fn tail_gen_system(
+    time: Res<Time>,
+    hitch_guard: Res<HitchGuard>,
+    mut state: Local<TailGenState>,
+    mut tail_timer: ResMut<TailTimer>,
+    grid: Res<TailGrid>,
+    mouse_pos: Res<MousePos>,
+    interpolation: Res<RenderInterpolation>,
+    idle_retract: Res<IdleRetract>,
+    dead_zone: Res<DeadZone>,
+    center: Res<CenterPlayer>,
+    push_cap: Res<TailPushCap>,
+    unfocused: Res<WindowUnfocused>,
+    follow: Res<TailFollow>,
+    mut pushed_events: ResMut<Events<TailNodePushed>>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Player,
+            &mut SimPosition,
+            &mut Idle,
+            &InputSource,
+            Option<&MoveTarget>,
+            Option<&BezierPath>,
+        ),
+        (Without<ReplayTail>, Without<Rewinding>),
+    >,
+) {
+    // the timer isn't ticked while away, so nothing piles up for when focus is back,
+    // and the head is handled like after a hitch in case the player moved meanwhile
+    if unfocused.0 {
+        state.after_hitch = true;
+        return;
+    }
+    // a long frame would otherwise burst the timer and draw a segment across the jump
+    if hitch_guard.is_hitch(&time) {
+        state.after_hitch = true;
+        return;
+    }
+    // the timer wraps, so ticks missed by a slow frame have to be counted before it does
+    let elapsed_before = tail_timer.0.elapsed();
+    let due = ((elapsed_before + time.delta_seconds()) / tail_timer.0.duration()) as usize;
+    tail_timer.0.tick(time.delta_seconds());
+    let frame_starts = std::mem::take(&mut state.last_positions);
+    for (entity, trans, ..) in query.iter_mut() {
+        state
+            .last_positions
+            .insert(entity, trans.translation.truncate());
+    }
+    if !tail_timer.0.finished() {
+        return;
+    }
+    let pushes = due.clamp(1, push_cap.max_per_frame.max(1));
+    let after_hitch = std::mem::take(&mut state.after_hitch);
+    let tick = tail_timer.0.duration();
+    for (entity, trans, mut player, mut sim, mut idle, source, target, path) in query.iter_mut() {
+        let mouse_driven = matches!(source, InputSource::Mouse)
+            && target.is_none()
+            && path.is_none()
+            && !center.enabled;
+        let pos = if *follow == TailFollow::Mouse {
+            mouse_pos.0
+        } else if interpolation.0 && mouse_driven {
+            sim.previous = sim.current;
+            if sim.current.distance(mouse_pos.0) >= dead_zone.radius {
+                sim.current = mouse_pos.0;
+            }
+            sim.current
+        } else {
+            Vec2::new(trans.translation.x, trans.translation.y)
+        };
+        let jumped = player
+            .tail
+            .first()
+            .is_some_and(|head| head.pos.distance(pos) > hitch_guard.max_jump);
+        if after_hitch && jumped {
+            player.tail.clear();
+        }
+        if pos.distance_squared(idle.last_pos) < 0.25 {
+            idle.time += tick;
+        } else {
+            *idle = Idle {
+                last_pos: pos,
+                ..Default::default()
+            };
+        }
+        if idle_retract.enabled && idle.time >= idle_retract.timeout {
+            idle.carry += idle_retract.rate * tick;
+            let count = idle.carry as usize;
+            idle.carry -= count as f32;
+            player.consume_tail(count);
+            continue;
+        }
+        // holding still inside the dead zone adds nothing on top of the head
+        let pos = grid.snap(pos);
+        if dead_zone.radius > 0.
+            && mouse_driven
+            && player.tail.first().is_some_and(|head| head.pos == pos)
+        {
+            continue;
+        }
+        // catching up fills in between, except across a teleport, which gets its gap
+        let from = match player.tail.first() {
+            Some(head) if head.pos.distance(pos) <= TELEPORT_DISTANCE => head.pos,
+            _ => pos,
+        };
+        // the mouse driven interpolation has its own previous position, and a hitch
+        // leaves the frame start stale
+        let frame_start = frame_starts
+            .get(&entity)
+            .copied()
+            .filter(|_| push_cap.interpolate && pushes > 1 && !after_hitch)
+            .filter(|_| !(interpolation.0 && mouse_driven))
+            .filter(|start| start.distance(pos) <= TELEPORT_DISTANCE);
+        for k in 1..=pushes {
+            let at = match frame_start {
+                // the last `pushes` of the due ticks, as a fraction of the frame
+                Some(start) => {
+                    let tick_at = (due - pushes + k) as f32 * tick - elapsed_before;
+                    start.lerp(pos, (tick_at / time.delta_seconds()).clamp(0., 1.))
+                }
+                None if k == pushes => pos,
+                None => from.lerp(pos, k as f32 / pushes as f32),
+            };
+            if player.push_tail_node(at) {
+                pushed_events.send(TailNodePushed { player: entity });
+            }
+        }
+        // player.make_debug_tail(pos);
+    }
+}
+
+// nodes walked back per second while R is held
+struct Rewind {
+    rate: f32,
+}
+
+// the player retraces its tail head first instead of following input
+#[derive(Default)]
+struct Rewinding {
+    carry: f32,
+}
+
+fn rewind_input_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut players: Query<(Entity, &mut Player, &mut Transform), InputDriven>,
+    rewinding: Query<Entity, With<Rewinding>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::R) {
+        // with shift the tail is flipped first, so the player walks it from its oldest node
+        let flip =
+            keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+        for (entity, mut player, mut trans) in players.iter_mut() {
+            if flip {
+                player.reverse_tail();
+                if let Some(head) = player.tail.first() {
+                    trans.translation.x = head.pos.x;
+                    trans.translation.y = head.pos.y;
+                }
+            }
+            commands.insert_one(entity, Rewinding::default());
+        }
+    }
+    if keyboard_input.just_released(KeyCode::R) {
+        for entity in rewinding.iter() {
+            commands.remove_one::<Rewinding>(entity);
+        }
+    }
+}
+
+fn rewind_system(
+    time: Res<Time>,
+    rewind: Res<Rewind>,
+    mut query: Query<(&mut Transform, &mut Player, &mut Rewinding)>,
+) {
+    for (mut trans, mut player, mut rewinding) in query.iter_mut() {
+        rewinding.carry += rewind.rate * time.delta_seconds();
+        let count = rewinding.carry as usize;
+        rewinding.carry -= count as f32;
+        // the last node stays so the player has somewhere to stand
+        let count = count.min(player.tail.len().saturating_sub(1));
+        player.tail.drain(..count);
+        if let Some(head) = player.tail.first() {
+            trans.translation.x = head.pos.x;
+            trans.translation.y = head.pos.y;
+        }
+    }
+}
+
+fn player_color_system(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(&PlayerColor, &Handle<ColorMaterial>), (With<Player>, Changed<PlayerColor>)>,
+) {
+    for (color, handle) in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color.0;
+        }
+    }
+}
+
+fn beat_intensity_system(
+    beat: Res<BeatStrength>,
+    beat_response: Res<BeatResponse>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    let intensity = 1. + beat.0 * beat_response.intensity_gain;
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            if (material.intensity - intensity).abs() > f32::EPSILON {
+                material.intensity = intensity;
+            }
+        }
+    }
+}
+
+fn velocity_system(time: Res<Time>, mut query: Query<(&Transform, &mut Velocity)>) {
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+    for (trans, mut velocity) in query.iter_mut() {
+        let pos = Vec2::new(trans.translation.x, trans.translation.y);
+        velocity.value = (pos - velocity.last_position) / dt;
+        velocity.last_position = pos;
+    }
+}
+
+fn prediction_system(
+    prediction: Res<TrajectoryPrediction>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Handle<Mesh>, &PredictionLine, &mut Visible)>,
+    players: Query<(&Transform, &Velocity), With<Player>>,
+) {
+    for (mesh_handle, line, mut visible) in query.iter_mut() {
+        let mesh = match meshes.get_mut(mesh_handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let player = line.player.and_then(|e| players.get(e).ok());
+        visible.is_visible = match player {
+            Some((trans, velocity)) if prediction.enabled => {
+                let head = Vec2::new(trans.translation.x, trans.translation.y);
+                make_prediction_mesh(mesh, head, velocity.value * prediction.horizon);
+                true
+            }
+            _ => {
+                clear_line_mesh(mesh);
+                false
+            }
+        };
+    }
+}
+
+// stretches the player quad along its velocity, the tail only reads the translation
+struct MotionStretch {
+    enabled: bool,
+    // length over width at full_speed and above
+    max_stretch: f32,
+    full_speed: f32,
+}
+
+impl Default for MotionStretch {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_stretch: 2.5,
+            full_speed: 2000.,
+        }
+    }
+}
+
+fn motion_stretch_system(
+    stretch: Res<MotionStretch>,
+    mut query: Query<(&mut Transform, &Velocity), With<Player>>,
+) {
+    if !stretch.enabled {
+        return;
+    }
+    for (mut trans, velocity) in query.iter_mut() {
+        let speed = velocity.value.length();
+        if speed <= f32::EPSILON || stretch.full_speed <= 0. {
+            trans.scale = Vec3::one();
+            trans.rotation = Quat::identity();
+            continue;
+        }
+        let t = (speed / stretch.full_speed).min(1.);
+        let length = 1. + (stretch.max_stretch - 1.).max(0.) * t;
+        trans.scale = Vec3::new(length, 1., 1.);
+        trans.rotation = Quat::from_rotation_z(velocity.value.y.atan2(velocity.value.x));
+    }
+}
+
+// pulses the player quad's scale once it has been slow for delay seconds,
+// on top of whatever MotionStretch left there
+struct IdleBreathing {
+    enabled: bool,
+    // speed below which the player counts as idle
+    threshold: f32,
+    delay: f32,
+    // fraction of the scale added and taken away at the peaks
+    amplitude: f32,
+    // pulses per second
+    frequency: f32,
+}
+
+impl Default for IdleBreathing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 5.,
+            delay: 1.,
+            amplitude: 0.08,
+            frequency: 0.5,
+        }
+    }
+}
+
+// seconds the pulse takes to fade in once idle and out once moving
+const BREATHING_EASE: f32 = 0.3;
+
+#[derive(Default)]
+struct BreathingState {
+    idle: f32,
+    weight: f32,
+    phase: f32,
+    // the scale written last frame and the factor in it, to take it back out
+    written: Vec3,
+    factor: f32,
+}
+
+fn idle_breathing_system(
+    time: Res<Time>,
+    breathing: Res<IdleBreathing>,
+    mut states: Local<HashMap<Entity, BreathingState>>,
+    mut query: Query<(Entity, &mut Transform, &Velocity), With<Player>>,
+) {
+    if !breathing.enabled {
+        return;
+    }
+    // despawned players take their state with them
+    states.retain(|entity, _| query.get_component::<Velocity>(*entity).is_ok());
+    let dt = time.delta_seconds();
+    for (entity, mut trans, velocity) in query.iter_mut() {
+        let state = states.entry(entity).or_default();
+        // unless something else set the scale since, like motion_stretch_system does
+        if state.factor > 0. && trans.scale == state.written {
+            trans.scale.x /= state.factor;
+            trans.scale.y /= state.factor;
+        }
+        if velocity.value.length() < breathing.threshold {
+            state.idle += dt;
+        } else {
+            state.idle = 0.;
+        }
+        let target = if state.idle >= breathing.delay {
+            1.
+        } else {
+            0.
+        };
+        let step = dt / BREATHING_EASE;
+        state.weight = if state.weight < target {
+            (state.weight + step).min(target)
+        } else {
+            (state.weight - step).max(target)
+        };
+        // every pulse starts from the rest size
+        if state.weight <= 0. {
+            state.phase = 0.;
+        } else {
+            state.phase += dt * breathing.frequency * std::f32::consts::TAU;
+        }
+        state.factor = 1. + breathing.amplitude * state.weight * state.phase.sin();
+        trans.scale.x *= state.factor;
+        trans.scale.y *= state.factor;
+        state.written = trans.scale;
+    }
+}
+
+fn hull_input_system(keyboard_input: Res<Input<KeyCode>>, mut hull: ResMut<HullDebug>) {
+    if keyboard_input.just_pressed(KeyCode::H) {
+        hull.enabled = !hull.enabled;
+    }
+}
+
+fn hull_system(
+    hull: Res<HullDebug>,
+    smoothing: Res<TailSmoothing>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(&Handle<Mesh>, &HullLine, &mut Visible)>,
+    players: Query<&Player>,
+) {
+    for (mesh_handle, line, mut visible) in query.iter_mut() {
+        let mesh = match meshes.get_mut(mesh_handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        visible.is_visible = match players.get(line.player) {
+            Ok(player) if hull.enabled => {
+                let tail = smoothing.smooth(player);
+                let hull = convex_hull(&ribbon_vertices(&tail));
+                make_hull_mesh(mesh, &hull);
+                hull.len() >= 2
+            }
+            _ => {
+                clear_line_mesh(mesh);
+                false
+            }
+        };
+    }
+}
+
+fn camera_shake_system(
+    time: Res<Time>,
+    shake: Res<CameraShake>,
+    mut rng: ResMut<RngResource>,
+    mut state: Local<ShakeState>,
+    players: Query<&Velocity, With<Player>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    for velocity in players.iter() {
+        if velocity.value.length() < shake.min_speed {
+            continue;
+        }
+        let direction = safe_normalize(velocity.value, Vec2::zero());
+        if state.last_direction != Vec2::zero()
+            && state.last_direction.angle_between(direction).abs() > shake.angle_threshold
+        {
+            state.trauma = 1.;
+        }
+        state.last_direction = direction;
+    }
+    state.trauma = (state.trauma - shake.decay * time.delta_seconds()).max(0.);
+    let jitter = Vec2::new(rng.0.gen::<f32>(), rng.0.gen::<f32>()) * 2. - Vec2::one();
+    let offset = jitter * shake.intensity * state.trauma * state.trauma;
+    // only the offset moves the camera, so input mapping keeps using the unshaken position
+    for mut trans in cameras.iter_mut() {
+        trans.translation.x += offset.x - state.offset.x;
+        trans.translation.y += offset.y - state.offset.y;
+    }
+    state.offset = offset;
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FollowEasing {
+    // straight at the player, world units per second
+    Linear { speed: f32 },
+    // closes `rate` of the remaining gap per second, slowing down as it arrives
+    EaseOut { rate: f32 },
+    // critically damped, higher frequency follows tighter, never overshoots
+    Spring { frequency: f32 },
+}
+
+// meant for keyboard and gamepad input, the mouse maps to world space around the origin
+struct CameraFollow {
+    enabled: bool,
+    easing: FollowEasing,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            easing: FollowEasing::Spring { frequency: 6. },
+        }
+    }
+}
+
+// treadmill view: the camera sits on the player while the tail streams past, and
+// the mouse player steers toward the cursor instead of jumping to it
+struct CenterPlayer {
+    enabled: bool,
+    // cursor distance from the screen center that moves at full Speed
+    steer_radius: f32,
+}
+
+impl Default for CenterPlayer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            steer_radius: 200.,
+        }
+    }
+}
+
+// how far framing the tail may pull the camera off the player, so the player stays in view
+const FOLLOW_TAIL_PULL: f32 = 200.;
+
+// the middle of the box around the player and its tail, pulled at most
+// FOLLOW_TAIL_PULL away from the player
+fn follow_target(position: Vec2, player: &Player) -> Vec2 {
+    let (min, max) = match player.tail_aabb() {
+        Some(aabb) => aabb,
+        None => return position,
+    };
+    let pull = (min.min(position) + max.max(position)) / 2. - position;
+    let length = pull.length();
+    if length > FOLLOW_TAIL_PULL {
+        position + pull * (FOLLOW_TAIL_PULL / length)
+    } else {
+        position + pull
+    }
+}
+
+#[derive(Default)]
+struct FollowState {
+    position: Vec2,
+    velocity: Vec2,
+}
+
+// L steps through off, linear, ease-out and spring
+fn camera_follow_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut follow: ResMut<CameraFollow>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::L) {
+        return;
+    }
+    let (enabled, easing) = match (follow.enabled, follow.easing) {
+        (false, _) => (true, FollowEasing::Linear { speed: 600. }),
+        (true, FollowEasing::Linear { .. }) => (true, FollowEasing::EaseOut { rate: 4. }),
+        (true, FollowEasing::EaseOut { .. }) => (true, FollowEasing::Spring { frequency: 6. }),
+        (true, FollowEasing::Spring { .. }) => (false, follow.easing),
+    };
+    follow.enabled = enabled;
+    follow.easing = easing;
+    if enabled {
+        info!("camera follow: {:?}", easing);
+    } else {
+        info!("camera follow: off");
+    }
+}
+
+fn camera_follow_system(
+    time: Res<Time>,
+    follow: Res<CameraFollow>,
+    center: Res<CenterPlayer>,
+    mut state: Local<FollowState>,
+    players: Query<(&Transform, &Player)>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    let target = if follow.enabled || center.enabled {
+        match players.iter().next() {
+            // the treadmill stays on the player, follow frames the tail too
+            Some((trans, _)) if center.enabled => {
+                Vec2::new(trans.translation.x, trans.translation.y)
+            }
+            Some((trans, player)) => {
+                follow_target(Vec2::new(trans.translation.x, trans.translation.y), player)
+            }
+            None => return,
+        }
+    } else {
+        // back to the origin once follow is turned off
+        Vec2::zero()
+    };
+    let dt = time.delta_seconds();
+    let offset = state.position - target;
+    let position = match follow.easing {
+        _ if center.enabled => target,
+        FollowEasing::Linear { speed } => {
+            let distance = offset.length();
+            if distance <= speed * dt {
+                target
+            } else {
+                state.position - offset / distance * speed * dt
+            }
+        }
+        FollowEasing::EaseOut { rate } => target + offset * (-rate * dt).exp(),
+        FollowEasing::Spring { frequency } => {
+            // exact step of x'' = -w^2 x - 2w x', stable for any dt
+            let decay = (-frequency * dt).exp();
+            let temp = (state.velocity + offset * frequency) * dt;
+            state.velocity = (state.velocity - temp * frequency) * decay;
+            target + (offset + temp) * decay
+        }
+    };
+    if center.enabled || !matches!(follow.easing, FollowEasing::Spring { .. }) {
+        state.velocity = Vec2::zero();
+    }
+    // moved by the difference like the shake, so the two stack
+    for mut trans in cameras.iter_mut() {
+        trans.translation.x += position.x - state.position.x;
+        trans.translation.y += position.y - state.position.y;
+    }
+    state.position = position;
+}
+
+fn tail_width_system(
+    time: Res<Time>,
+    speed_width: Res<SpeedWidth>,
+    easing: Res<WidthEasing>,
+    beat: Res<BeatStrength>,
+    beat_response: Res<BeatResponse>,
+    mut query: Query<&mut Player>,
+) {
+    let beat_scale = 1. + beat.0 * beat_response.width_gain;
+    let t = if easing.rate > 0. {
+        (easing.rate * time.delta_seconds()).min(1.0)
+    } else {
+        1.0
+    };
+    for mut player in query.iter_mut() {
+        if let NodeWidth::Fixed(_) = player.node_width {
+            continue;
+        }
+        let len = player.tail.len();
+        for i in 0..len {
+            // a jump across a gap is no speed
+            let tail = &player.tail;
+            let next = (i + 1 < len && !tail[i].gap).then(|| tail[i].pos.distance(tail[i + 1].pos));
+            let last = (i > 0 && !tail[i - 1].gap).then(|| tail[i - 1].pos.distance(tail[i].pos));
+            let speed = next.or(last).unwrap_or(0.);
+            let target = speed_width.width(speed) * beat_scale;
+            let node = &mut player.tail[i];
+            node.width += (target - node.width) * t;
+        }
+    }
+}
+
+fn interpolation_system(
+    tail_timer: Res<TailTimer>,
+    interpolation: Res<RenderInterpolation>,
+    center: Res<CenterPlayer>,
+    mut query: Query<(&mut Transform, &SimPosition, &InputSource), InputDriven>,
+) {
+    // steering moves the player every frame, there's nothing to interpolate
+    if !interpolation.0 || center.enabled {
+        return;
+    }
+    let alpha = tail_timer.0.percent();
+    for (mut trans, sim, source) in query.iter_mut() {
+        if !matches!(source, InputSource::Mouse) {
+            continue;
+        }
+        let pos = sim.previous.lerp(sim.current, alpha);
+        trans.translation.x = pos.x;
+        trans.translation.y = pos.y;
+    }
+}
+
+fn move_target_input_system(
+    commands: &mut Commands,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mouse_pos: Res<MousePos>,
+    query: Query<Entity, With<Player>>,
+) {
+    if !mouse_button_input.just_pressed(MouseButton::Right) {
+        return;
+    }
+    for entity in query.iter() {
+        commands.insert_one(entity, MoveTarget(mouse_pos.0));
+    }
+}
+
+fn move_target_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    movement: Res<TargetMovement>,
+    mut reached_events: ResMut<Events<ReachedTargetEvent>>,
+    mut query: Query<(Entity, &mut Transform, &MoveTarget, &Speed), Without<ReplayTail>>,
+) {
+    for (entity, mut trans, target, speed) in query.iter_mut() {
+        let max_step = speed.get() * time.delta_seconds();
+        let pos = Vec2::new(trans.translation.x, trans.translation.y);
+        let to_target = target.0 - pos;
+        let distance = to_target.length();
+        let next = if distance <= max_step {
+            target.0
+        } else {
+            pos + to_target / distance * max_step
+        };
+        trans.translation.x = next.x;
+        trans.translation.y = next.y;
+        if distance <= max_step {
+            reached_events.send(ReachedTargetEvent {
+                player: entity,
+                target: target.0,
+            });
+            if movement.clear_on_reach {
+                commands.remove_one::<MoveTarget>(entity);
+            }
+        }
+    }
+}
+
+// C starts an S curve from the player across the arena, C again stops it
+fn bezier_input_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    arena: Res<Arena>,
+    playback: Res<BezierPlayback>,
+    query: Query<(Entity, &Transform, Option<&BezierPath>), With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::C) {
+        return;
+    }
+    for (entity, trans, path) in query.iter() {
+        if path.is_some() {
+            commands.remove_one::<BezierPath>(entity);
+            continue;
+        }
+        let start = Vec2::new(trans.translation.x, trans.translation.y);
+        let end = Vec2::new(arena.min.x + arena.max.x, arena.min.y + arena.max.y) - start;
+        commands.insert_one(
+            entity,
+            BezierPath {
+                p0: start,
+                p1: Vec2::new(end.x, arena.max.y),
+                p2: Vec2::new(start.x, arena.min.y),
+                p3: end,
+                duration: playback.duration,
+                elapsed: 0.,
+                looping: playback.looping,
+            },
+        );
+    }
+}
+
+fn bezier_path_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    hitch_guard: Res<HitchGuard>,
+    mut query: Query<(Entity, &mut Transform, &mut BezierPath), Without<ReplayTail>>,
+) {
+    if hitch_guard.is_hitch(&time) {
+        return;
+    }
+    for (entity, mut trans, mut path) in query.iter_mut() {
+        path.elapsed += time.delta_seconds();
+        if path.elapsed >= path.duration {
+            if path.looping && path.duration > 0. {
+                path.elapsed %= path.duration;
+            } else {
+                path.elapsed = path.duration;
+                commands.remove_one::<BezierPath>(entity);
+            }
+        }
+        let t = if path.duration > 0. {
+            path.elapsed / path.duration
+        } else {
+            1.
+        };
+        let pos = path.point(t);
+        trans.translation.x = pos.x;
+        trans.translation.y = pos.y;
+    }
+}
+
+#[derive(Default)]
+struct ReachedTargetState {
+    reached_event_reader: EventReader<ReachedTargetEvent>,
+}
+
+fn reached_target_report_system(
+    mut state: Local<ReachedTargetState>,
+    reached_events: Res<Events<ReachedTargetEvent>>,
+) {
+    for event in state.reached_event_reader.iter(&reached_events) {
+        println!("{:?} reached {}", event.player, event.target);
+    }
+}
+
+fn tail_save_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(Entity, &mut Player)>,
+) {
+    let path = Path::new(SAVED_TAIL_PATH);
+    for (entity, mut player) in query.iter_mut() {
+        if keyboard_input.just_pressed(KeyCode::F5) {
+            match save::save_tail(&player.tail, path) {
+                Ok(()) => println!("tail saved to {}", SAVED_TAIL_PATH),
+                Err(err) => println!("failed to save tail: {}", err),
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::F9) {
+            match save::load_tail(path) {
+                Ok(nodes) => {
+                    commands.insert_one(entity, ReplayTail::start(&mut player, &nodes));
+                }
+                Err(err) => println!("failed to load tail: {}", err),
+            }
+        }
+    }
+}
+
+// F6 saves and F10 restores the player, its tail and the tail palette in one file,
+// a replay in progress is saved as plain mouse movement
+fn state_save_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    materials: Res<Assets<MyMaterialWithVertexColorSupport>>,
+    mut swap_events: ResMut<Events<PaletteSwapEvent>>,
+    mut query: Query<(
+        Entity,
+        &mut Player,
+        &mut Transform,
+        &mut SimPosition,
+        &mut Velocity,
+        Option<&MoveTarget>,
+    )>,
+    tails: Query<(&Tail, &Handle<MyMaterialWithVertexColorSupport>), Without<Outline>>,
+) {
+    let path = Path::new(SAVED_STATE_PATH);
+    for (entity, mut player, mut trans, mut sim, mut velocity, target) in query.iter_mut() {
+        if keyboard_input.just_pressed(KeyCode::F6) {
+            let palette = tails
+                .iter()
+                .find(|(tail, _)| tail.player == Some(entity))
+                .and_then(|(_, handle)| materials.get(handle))
+                .map(|material| material.palette.clone())
+                .unwrap_or_default();
+            let state = save::SavedState {
+                version: save::STATE_VERSION,
+                position: Vec2::new(trans.translation.x, trans.translation.y),
+                tail: player.tail.clone(),
+                tail_len: player.tail_len,
+                palette,
+                movement: match target {
+                    Some(target) => save::MovementMode::Target(target.0),
+                    None => save::MovementMode::Mouse,
+                },
+            };
+            match save::save_state(&state, path) {
+                Ok(()) => println!("state saved to {}", SAVED_STATE_PATH),
+                Err(err) => println!("failed to save state: {}", err),
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::F10) {
+            let state = match save::load_state(path) {
+                Ok(state) => state,
+                Err(err) => {
+                    println!("failed to load state: {}", err);
+                    continue;
+                }
+            };
+            trans.translation.x = state.position.x;
+            trans.translation.y = state.position.y;
+            *sim = SimPosition {
+                previous: state.position,
+                current: state.position,
+            };
+            *velocity = Velocity {
+                value: Vec2::zero(),
+                last_position: state.position,
+            };
+            player.tail = state.tail;
+            player.set_tail_len(state.tail_len);
+            commands.remove_one::<ReplayTail>(entity);
+            match state.movement {
+                save::MovementMode::Target(target) => {
+                    commands.insert_one(entity, MoveTarget(target));
+                }
+                save::MovementMode::Mouse => {
+                    commands.remove_one::<MoveTarget>(entity);
+                }
+            }
+            if state.palette.len() == palette::PALETTE_STOPS {
+                let mut stops = palette::rainbow_stops();
+                stops.copy_from_slice(&state.palette);
+                swap_events.send(PaletteSwapEvent { stops });
+            } else {
+                println!(
+                    "saved palette has {} stops, keeping the current one",
+                    state.palette.len()
+                );
+            }
+        }
+    }
+}
+
+fn replay_system(
+    commands: &mut Commands,
+    tail_timer: Res<TailTimer>,
+    replay_speed: Res<ReplaySpeed>,
+    mut query: Query<(Entity, &mut Transform, &mut Player, &mut ReplayTail)>,
+) {
+    if !tail_timer.0.finished() {
+        return;
+    }
+    for (entity, mut trans, mut player, mut replay) in query.iter_mut() {
+        replay.progress += replay_speed.0;
+        while replay.progress >= 1. && replay.next < replay.nodes.len() {
+            let node = replay.nodes[replay.next];
+            // push_tail_node would recompute velocity, width and gaps from the
+            // positions, the saved nodes already have theirs
+            player.push_node(node);
+            trans.translation.x = node.pos.x;
+            trans.translation.y = node.pos.y;
+            replay.next += 1;
+            replay.progress -= 1.;
+        }
+        if replay.next >= replay.nodes.len() {
+            commands.remove_one::<ReplayTail>(entity);
+        }
+    }
+}
+
+// runs before tail_system, so the ghost shows exactly the previous rebuild
+fn ghost_tail_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut ghost_tail: ResMut<GhostTail>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    mut set: QuerySet<(
+        Query<(
+            Entity,
+            &Ghost,
+            &mut Handle<Mesh>,
+            &mut Visible,
+            &Handle<MyMaterialWithVertexColorSupport>,
+        )>,
+        Query<
+            (
+                &mut Handle<Mesh>,
+                &Visible,
+                &Handle<MyMaterialWithVertexColorSupport>,
+            ),
+            With<Tail>,
+        >,
+    )>,
+) {
+    if keyboard_input.just_pressed(KeyCode::G) {
+        ghost_tail.enabled = !ghost_tail.enabled;
+        info!("ghost tail: {}", ghost_tail.enabled);
+    }
+    let ghosts: Vec<_> = set
+        .q0_mut()
+        .iter_mut()
+        .map(|(entity, ghost, mesh, _, material)| {
+            (entity, ghost.tail, mesh.clone(), material.clone())
+        })
+        .collect();
+    for (entity, tail, ghost_mesh, ghost_material) in ghosts {
+        let (last_mesh, tail_visible, tail_material) = match set.q1_mut().get_mut(tail) {
+            Ok((mut mesh, visible, material)) if ghost_tail.enabled => (
+                std::mem::replace(&mut *mesh, ghost_mesh),
+                visible.is_visible,
+                material.clone(),
+            ),
+            _ => {
+                if let Ok((_, _, _, mut visible, _)) = set.q0_mut().get_mut(entity) {
+                    visible.is_visible = false;
+                }
+                continue;
+            }
+        };
+        let tail_look = materials
+            .get(&tail_material)
+            .map(|material| (material.palette.clone(), material.global_alpha));
+        if let (Some((palette, alpha)), Some(material)) =
+            (tail_look, materials.get_mut(&ghost_material))
+        {
+            material.palette = palette;
+            material.global_alpha = alpha * ghost_tail.alpha;
+        }
+        if let Ok((_, _, mut mesh, mut visible, _)) = set.q0_mut().get_mut(entity) {
+            *mesh = last_mesh;
+            visible.is_visible = tail_visible;
+        }
+    }
+}
+
+// seconds after startup the rendered tail takes to grow to its full length, 0 is instant
+#[derive(Default)]
+struct StartupGrow {
+    duration: f32,
+}
+
+impl StartupGrow {
+    // how many of `len` nodes get drawn right now
+    fn grown_len(&self, time: &Time, len: usize) -> usize {
+        if self.duration <= 0. {
+            return len;
+        }
+        let t = (time.seconds_since_startup() as f32 / self.duration).min(1.);
+        // eased so the start isn't a sudden jump
+        let t = t * t * (3. - 2. * t);
+        ((len as f32 * t).ceil() as usize).min(len)
+    }
+}
+
+// tail_system skips these tails, so this is all that happens to them
+fn orphan_tail_system(
+    commands: &mut Commands,
+    orphans: Res<OrphanTailPolicy>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut remeshed: ResMut<Events<TailRemeshed>>,
+    tails: Query<(Entity, &Handle<Mesh>, &Tail)>,
+    players: Query<(), With<Player>>,
+) {
+    for (entity, mesh_handle, tail) in tails.iter() {
+        if tail
+            .player
+            .is_some_and(|player| players.get(player).is_ok())
+        {
+            continue;
+        }
+        match *orphans {
+            OrphanTailPolicy::Hide => {
+                let mesh = meshes.get_mut(mesh_handle).unwrap();
+                if !matches!(mesh.indices(), Some(Indices::U16(i)) if i.is_empty()) {
+                    make_tail_mesh(mesh, &[]);
+                    remeshed.send(TailRemeshed { entity });
+                }
+            }
+            OrphanTailPolicy::Freeze => {}
+            OrphanTailPolicy::Despawn => {
+                info!("despawning the tail of a missing player");
+                commands.despawn(entity);
+            }
+        }
+    }
+}
+
+fn tail_system(
+    time: Res<Time>,
+    grow: Res<StartupGrow>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    // grouped, a system takes at most 16 parameters
+    (stroke, extrude): (Res<TailStroke>, Res<Extrude3d>),
+    smoothing: Res<TailSmoothing>,
+    color_source: Res<TailColorSource>,
+    speed_width: Res<SpeedWidth>,
+    arena: Res<Arena>,
+    idle_color: Res<IdleColor>,
+    (decay, decimation, curvature): (Res<TrailDecay>, Res<TailDecimation>, Res<CurvatureLimit>),
+    (precision, tail_pipelines): (Res<AttributePrecision>, Res<TailPipelines>),
+    (screen_width, cameras): (Res<ScreenSpaceWidth>, Query<&Transform, With<MainCamera>>),
+    mut remeshed: ResMut<Events<TailRemeshed>>,
+    mut query: Query<(
+        Entity,
+        &Handle<Mesh>,
+        &Tail,
+        Option<&Outline>,
+        Option<&Extruded>,
+        &RenderPipelines,
+    )>,
+    query_a: Query<(&Player, &Transform)>,
+) {
+    // the camera's scale stands in for zoom, like for the labels
+    let zoom = cameras
+        .iter()
+        .next()
+        .map_or(1., |trans| trans.scale.x.max(f32::EPSILON));
+    for (entity, mesh_handle, tail, outline, extruded, render_pipelines) in query.iter_mut() {
+        let player = match tail.player.map(|e| query_a.get_component::<Player>(e)) {
+            Some(Ok(player)) => player,
+            _ => continue,
+        };
+        let mesh = meshes.get_mut(mesh_handle).unwrap();
+        let mut nodes = smoothing.smooth(player);
+        nodes.truncate(grow.grown_len(&time, nodes.len()));
+        let nodes = decimate_tail(&nodes, decimation.tolerance);
+        let mut nodes = round_tail_corners(&nodes, curvature.max_angle);
+        if arena.clip_tail {
+            nodes = clip_tail_to_arena(&nodes, &arena);
+        }
+        screen_width.apply(&mut nodes, zoom);
+        if outline.is_some() {
+            make_outline_mesh(mesh, &nodes, stroke.width);
+        } else {
+            if extruded.is_some() {
+                make_tail_mesh_3d(mesh, &nodes, extrude.depth);
+            } else {
+                make_tail_mesh(mesh, &nodes);
+            }
+            if let Some(colors) = color_source.node_colors(&nodes, &speed_width, &arena) {
+                set_node_colors(mesh, &colors);
+            }
+            if idle_color.enabled {
+                set_idle_nodes(mesh, &nodes, idle_color.threshold);
+            }
+            if decay.enabled {
+                set_node_decay(mesh, &nodes, decay.rate);
+            }
+            // render_dimension_system may have switched the pipeline before the marker lands
+            let to_extruded = render_pipelines
+                .pipelines
+                .iter()
+                .any(|p| p.pipeline == tail_pipelines.extruded);
+            if *precision == AttributePrecision::Packed && extruded.is_none() && !to_extruded {
+                pack_ribbon_attributes(mesh);
+            }
+        }
+        remeshed.send(TailRemeshed { entity });
+    }
+}
+
+fn stamp_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    smoothing: Res<TailSmoothing>,
+    mut cache: ResMut<StampMeshCache>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<
+        (
+            &Tail,
+            &Handle<MyMaterialWithVertexColorSupport>,
+            &RenderPipelines,
+            &Transform,
+        ),
+        (Without<Outline>, Without<Extruded>),
+    >,
+    query_a: Query<&Player>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::T) {
+        return;
+    }
+    for (tail, material, render_pipelines, tail_trans) in query.iter() {
+        let player = match tail.player.map(|e| query_a.get(e)) {
+            Some(Ok(player)) => player,
+            _ => continue,
+        };
+        let nodes = smoothing.smooth(player);
+        if nodes.len() < 2 {
+            continue;
+        }
+        // a copy, so later palette, alpha or decay changes to the tail leave it frozen
+        let material = match materials.get(material).cloned() {
+            Some(material) => materials.add(material),
+            None => continue,
+        };
+        let mesh = cache
+            .meshes
+            .entry(stamp_key(&nodes))
+            .or_insert_with(|| {
+                let mut mesh = make_mesh(&[], vec![]);
+                make_tail_mesh(&mut mesh, &nodes);
+                meshes.add(mesh)
+            })
+            .clone();
+        commands
+            .spawn(MeshBundle {
+                mesh,
+                render_pipelines: render_pipelines.clone(),
+                transform: Transform::from_translation(Vec3::new(
+                    0.0,
+                    0.0,
+                    tail_trans.translation.z + STAMP_Z,
+                )),
+                ..Default::default()
+            })
+            .with(material)
+            .with(Stamp);
+    }
+}
+
+// drops meshes no stamp points at anymore, before stamp_system so a mesh it just
+// cached isn't dropped before its stamp is spawned
+fn stamp_cache_system(
+    mut cache: ResMut<StampMeshCache>,
+    stamps: Query<&Handle<Mesh>, With<Stamp>>,
+) {
+    if cache.meshes.is_empty() {
+        return;
+    }
+    let live: HashSet<HandleId> = stamps.iter().map(|mesh| mesh.id).collect();
+    cache.meshes.retain(|_, mesh| live.contains(&mesh.id));
+}
+
+// second ribbon forked off a player's tail with F, its head mirrors the player's
+// movement since the fork rotated by BranchConfig::angle around the branch point
+struct BranchTail {
+    player: Entity,
+    origin: Vec2,
+    // not a component, just the node history and its push rules
+    history: Player,
+}
+
+struct BranchConfig {
+    angle: f32,
+}
+
+fn rotate(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+fn fork_input_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    smoothing: Res<TailSmoothing>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    branches: Query<Entity, With<BranchTail>>,
+    tails: Query<
+        (
+            &Tail,
+            &Handle<MyMaterialWithVertexColorSupport>,
+            &RenderPipelines,
+            &Transform,
+        ),
+        (Without<Outline>, Without<Extruded>),
+    >,
+    players: Query<(&Player, &Transform)>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F) {
+        return;
+    }
+    // a second F joins the branches back
+    if branches.iter().next().is_some() {
+        for entity in branches.iter() {
+            commands.despawn(entity);
+        }
+        return;
+    }
+    for (tail, material, render_pipelines, tail_trans) in tails.iter() {
+        let player_entity = match tail.player {
+            Some(entity) => entity,
+            None => continue,
+        };
+        let (player, trans) = match players.get(player_entity) {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+        // seeded with the shared history, so both ribbons are one up to the fork
+        let history = Player {
+            size: player.size,
+            tail: player.tail.clone(),
+            tail_len: player.tail_len,
+            node_width: player.node_width,
+            position: player.position,
+        };
+        // built now, it's drawn before branch_tail_system ever sees it
+        let mut mesh = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut mesh, &smoothing.smooth(&history));
+        let z = tail_trans.translation.z + BRANCH_Z;
+        commands
+            .spawn(MeshBundle {
+                mesh: meshes.add(mesh),
+                render_pipelines: render_pipelines.clone(),
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, z)),
+                ..Default::default()
+            })
+            .with(material.clone())
+            .with(BranchTail {
+                player: player_entity,
+                origin: Vec2::new(trans.translation.x, trans.translation.y),
+                history,
+            });
+    }
+}
+
+fn branch_tail_system(
+    tail_timer: Res<TailTimer>,
+    config: Res<BranchConfig>,
+    smoothing: Res<TailSmoothing>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut branches: Query<(&mut BranchTail, &Handle<Mesh>)>,
+    players: Query<&Transform, With<Player>>,
+) {
+    for (mut branch, mesh_handle) in branches.iter_mut() {
+        if tail_timer.0.finished() {
+            if let Ok(trans) = players.get(branch.player) {
+                let pos = Vec2::new(trans.translation.x, trans.translation.y);
+                let head = branch.origin + rotate(pos - branch.origin, config.angle);
+                branch.history.push_tail_node(head);
+            }
+        }
+        if let Some(mesh) = meshes.get_mut(mesh_handle) {
+            make_tail_mesh(mesh, &smoothing.smooth(&branch.history));
+        }
+    }
+}
+
+fn spawn_time_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    query: Query<Entity, (With<Player>, Without<SpawnTime>)>,
+) {
+    for entity in query.iter() {
+        commands.insert_one(entity, SpawnTime(time.seconds_since_startup()));
+    }
+}
+
+// players still in their grace period blink, only while collisions are on
+fn spawn_grace_blink_system(
+    time: Res<Time>,
+    collision: Res<TailCollision>,
+    grace: Res<SpawnGrace>,
+    follow: Res<TailFollow>,
+    mut query: Query<(&SpawnTime, &mut Visible), With<Player>>,
+) {
+    for (spawn_time, mut visible) in query.iter_mut() {
+        let blinking = collision.enabled && spawn_time.in_grace(&time, &grace);
+        let elapsed = time.seconds_since_startup() - spawn_time.0;
+        // the ribbon cursor keeps the quad hidden, see tail_follow_system
+        let shown = *follow == TailFollow::Player
+            && (!blinking || (elapsed * grace.blink_rate as f64).fract() < 0.5);
+        if visible.is_visible != shown {
+            visible.is_visible = shown;
+        }
+    }
+}
+
+fn tail_collision_system(
+    time: Res<Time>,
+    collision: Res<TailCollision>,
+    grace: Res<SpawnGrace>,
+    mut crossed_events: ResMut<Events<CrossedTailEvent>>,
+    query: Query<(Entity, &Player, &Transform, Option<&SpawnTime>)>,
+) {
+    if !collision.enabled {
+        return;
+    }
+    // the spawn time comes a frame late, until then the player counts as just spawned
+    let in_grace =
+        |spawn_time: Option<&SpawnTime>| spawn_time.is_none_or(|t| t.in_grace(&time, &grace));
+    for (crosser, crosser_player, trans, crosser_spawn) in query.iter() {
+        if in_grace(crosser_spawn) {
+            continue;
+        }
+        let head = Vec2::new(trans.translation.x, trans.translation.y);
+        let threshold = crosser_player.size / 2.;
+        for (owner, owner_player, _, owner_spawn) in query.iter() {
+            if owner == crosser || in_grace(owner_spawn) {
+                continue;
+            }
+            let (min, max) = match owner_player.tail_aabb() {
+                Some(aabb) => aabb,
+                None => continue,
+            };
+            // skip tails that can't be reached before walking their edges
+            let reach = owner_player
+                .tail
+                .iter()
+                .fold(0., |reach: f32, node| reach.max(node.width))
+                + threshold;
+            if (head.cmplt(min - Vec2::splat(reach)) | head.cmpgt(max + Vec2::splat(reach))).any() {
+                continue;
+            }
+            let crossed = make_ribbon_edges(owner_player)
+                .into_iter()
+                .any(|(a, b)| point_segment_distance(head, a, b) < threshold);
+            if crossed {
+                crossed_events.send(CrossedTailEvent { crosser, owner });
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct CrossedTailState {
+    crossed_event_reader: EventReader<CrossedTailEvent>,
+}
+
+fn crossed_tail_report_system(
+    mut state: Local<CrossedTailState>,
+    crossed_events: Res<Events<CrossedTailEvent>>,
+) {
+    for event in state.crossed_event_reader.iter(&crossed_events) {
+        println!("{:?} crossed the tail of {:?}", event.crosser, event.owner);
+    }
+}
+
+fn boost_input_system(
+    commands: &mut Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    boost: Res<SpeedBoost>,
+    mut consume_events: ResMut<Events<ConsumeTailEvent>>,
+    mut query: Query<(Entity, &mut Speed, Option<&mut Boosted>), With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for (player, mut speed, boosted) in query.iter_mut() {
+        consume_events.send(ConsumeTailEvent {
+            player,
+            count: BOOST_COST,
+        });
+        match boosted {
+            // boosting again only extends it, the factor doesn't stack
+            Some(mut boosted) => boosted.remaining = boost.duration,
+            None => {
+                let base = speed.get();
+                speed.set(base * boost.factor);
+                commands.insert_one(
+                    player,
+                    Boosted {
+                        base,
+                        remaining: boost.duration,
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn speed_boost_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Speed, &mut Boosted)>,
+) {
+    for (entity, mut speed, mut boosted) in query.iter_mut() {
+        boosted.remaining -= time.delta_seconds();
+        if boosted.remaining <= 0. {
+            speed.set(boosted.base);
+            commands.remove_one::<Boosted>(entity);
+        }
+    }
+}
+
+fn tail_length_input_system(keyboard_input: Res<Input<KeyCode>>, mut query: Query<&mut Player>) {
+    let grow = keyboard_input.just_pressed(KeyCode::Equals)
+        || keyboard_input.just_pressed(KeyCode::NumpadAdd);
+    let shrink = keyboard_input.just_pressed(KeyCode::Minus)
+        || keyboard_input.just_pressed(KeyCode::NumpadSubtract);
+    if grow == shrink {
+        return;
+    }
+    for mut player in query.iter_mut() {
+        let len = if grow {
+            player.tail_len + TAIL_LEN_STEP
+        } else {
+            player.tail_len.saturating_sub(TAIL_LEN_STEP)
+        };
+        player.set_tail_len(len);
+        info!("tail length {}", player.tail_len);
+    }
+}
+
+#[derive(Default)]
+struct ConsumeTailState {
+    consume_event_reader: EventReader<ConsumeTailEvent>,
+}
+
+fn consume_tail_system(
+    mut state: Local<ConsumeTailState>,
+    consume_events: Res<Events<ConsumeTailEvent>>,
+    mut query: Query<&mut Player>,
+) {
+    for event in state.consume_event_reader.iter(&consume_events) {
+        if let Ok(mut player) = query.get_mut(event.player) {
+            player.consume_tail(event.count);
+        }
+    }
+}
+
+#[derive(Default)]
+struct PaletteCycleState {
+    index: usize,
+}
+
+fn palette_cycle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut state: Local<PaletteCycleState>,
+    mut swap_events: ResMut<Events<PaletteSwapEvent>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::P) {
+        return;
+    }
+    state.index = (state.index + 1) % palette::PRESETS.len();
+    let (name, colors) = &palette::PRESETS[state.index];
+    info!("palette: {}", name);
+    swap_events.send(PaletteSwapEvent {
+        stops: palette::make_stops(colors),
+    });
+}
+
+fn startup_palette_system(
+    startup_palette: Res<StartupPalette>,
+    mut swap_events: ResMut<Events<PaletteSwapEvent>>,
+) {
+    if let Some(stops) = startup_palette.0 {
+        swap_events.send(PaletteSwapEvent { stops });
+    }
+}
+
+// seconds a palette swap takes to morph into the new stops, 0 swaps instantly
+struct PaletteTransition {
+    duration: f32,
+}
+
+#[derive(Default)]
+struct PaletteSwapState {
+    swap_event_reader: EventReader<PaletteSwapEvent>,
+    from: Vec<Vec4>,
+    to: Vec<Vec4>,
+    elapsed: f32,
+}
+
+impl PaletteSwapState {
+    fn blended(&self, duration: f32) -> Vec<Vec4> {
+        let t = if duration > 0. {
+            (self.elapsed / duration).min(1.)
+        } else {
+            1.
+        };
+        self.from
+            .iter()
+            .zip(&self.to)
+            .map(|(from, to)| from.lerp(*to, t))
+            .collect()
+    }
+}
+
+fn palette_swap_system(
+    time: Res<Time>,
+    transition: Res<PaletteTransition>,
+    mut state: Local<PaletteSwapState>,
+    swap_events: Res<Events<PaletteSwapEvent>>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    let mut swapped = false;
+    for event in state.swap_event_reader.iter(&swap_events) {
+        // a swap mid-transition starts from whatever is on screen right now
+        let from = if state.to.is_empty() {
+            query
+                .iter()
+                .find_map(|handle| materials.get(handle))
+                .map(|material| material.palette.clone())
+                .unwrap_or_else(|| event.stops.to_vec())
+        } else {
+            state.blended(transition.duration)
+        };
+        state.from = from;
+        state.to = event.stops.to_vec();
+        state.elapsed = 0.;
+        swapped = true;
+    }
+    if state.to.is_empty() || (!swapped && state.elapsed >= transition.duration) {
+        return;
+    }
+    if !swapped {
+        state.elapsed += time.delta_seconds();
+    }
+    let palette = state.blended(transition.duration);
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.palette = palette.clone();
+        }
+    }
+}
+
+fn tail_fade_system(
+    time: Res<Time>,
+    fade: Res<TailFade>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, With<Tail>>,
+) {
+    for handle in query.iter() {
+        let current = match materials.get(handle) {
+            Some(material) => material.global_alpha,
+            None => continue,
+        };
+        if (current - fade.target).abs() < f32::EPSILON {
+            continue;
+        }
+        let step = fade.speed * time.delta_seconds();
+        let next = if current < fade.target {
+            (current + step).min(fade.target)
+        } else {
+            (current - step).max(fade.target)
+        };
+        set_global_alpha(&mut materials, handle, next);
+    }
+}
+
+// gaps[i] drops the segment between node i and i + 1, missing entries draw it
+pub fn make_tail_indices(len: usize, gaps: &[bool]) -> Vec<u16> {
+    let mut triangles = vec![];
+    if len < 2 {
+        return vec![];
+    }
+    let gap = |i: usize| gaps.get(i).copied().unwrap_or(false);
+    for i in 0..len - 1 {
+        if gap(i) {
+            continue;
+        }
+        triangles.push((i, i + 1, 2 * i + len));
+        triangles.push((i + 1, 2 * i + len, 2 * i + len + 1));
+    }
+    // the wedge at node i joins segment i - 1 to segment i
+    for i in 1..len - 1 {
+        if gap(i - 1) || gap(i) {
+            continue;
+        }
+        triangles.push((i, 2 * i + len - 1, 2 * i + len));
+    }
+    triangles
+        .into_iter()
+        .flat_map(|(a, b, c)| vec![a as u16, b as u16, c as u16])
+        .collect()
+}
+
+// normalize() of a zero or tiny vector is NaN, fall back instead
+fn safe_normalize(v: Vec2, fallback: Vec2) -> Vec2 {
+    let length = v.length();
+    if length > f32::EPSILON && length.is_finite() {
+        v / length
+    } else {
+        fallback
+    }
+}
+
+fn get_normal(velocity: Vec2) -> Vec2 {
+    // anti-clock 90 deg, a node that never moved collapses onto the centerline
+    safe_normalize(Vec2::new(velocity.y, -velocity.x), Vec2::zero())
+}
+
+fn make_sub_tail(tail: &[TailNode], main_tail: &[Vec2], extra_width: f32) -> Vec<Vec2> {
+    if main_tail.len() < 2 {
+        return vec![];
+    }
+    let mut sub_tail = vec![Vec2::zero(); (main_tail.len() - 1) * 2];
+    for i in 0..tail.len() {
+        let normal = get_normal(tail[i].velocity);
+        let width = tail[i].width + extra_width;
+        if i == 0 {
+            sub_tail[0] = main_tail[0] + normal * width;
+        } else if i < tail.len() - 1 {
+            let normal_last = get_normal(tail[i - 1].velocity);
+            let width_last = tail[i - 1].width + extra_width;
+            sub_tail[2 * i - 1] = main_tail[i] + normal_last * width_last;
+            sub_tail[2 * i] = main_tail[i] + normal * width;
+        } else {
+            sub_tail[2 * i - 1] = main_tail[i] + normal * width;
+        }
+    }
+    sub_tail
+}
+
+// one node list running from the end of a's tail through both heads to the end of
+// b's, the segment between the heads bridges the two; a's half is walked backwards,
+// so its gaps and velocities are redone like reverse_tail does, b's are kept
+pub fn merge_tails(a: &Player, b: &Player) -> Vec<TailNode> {
+    let mut merged: Vec<TailNode> = a.tail.iter().rev().copied().collect();
+    let bridge = merged.len();
+    // a gap belongs to the segment after its node, which is now the segment before;
+    // a's head has none, that segment is the bridge
+    for i in 0..bridge {
+        merged[i].gap = i + 1 < bridge && merged[i + 1].gap;
+    }
+    merged.extend(b.tail.iter().copied());
+    for i in (0..bridge).rev() {
+        merged[i].velocity = match merged.get(i + 1).copied() {
+            Some(older) => {
+                let step = merged[i].pos - older.pos;
+                if merged[i].gap || step.length_squared() < 2. {
+                    older.velocity
+                } else {
+                    step
+                }
+            }
+            None => -merged[i].velocity,
+        };
+    }
+    merged
+}
+
+// inner and outer edge of every ribbon quad, same layout as make_tail_indices
+fn make_ribbon_edges(player: &Player) -> Vec<(Vec2, Vec2)> {
+    let main_tail = player.centerline();
+    let sub_tail = make_sub_tail(&player.tail, &main_tail, 0.);
+    let mut edges: Vec<_> = player.segments().map(|(a, b, _)| (a, b)).collect();
+    for i in 0..main_tail.len().saturating_sub(1) {
+        if player.tail[i].gap {
+            continue;
+        }
+        edges.push((sub_tail[2 * i], sub_tail[2 * i + 1]));
+    }
+    edges
+}
+
+fn point_segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let len_squared = ab.length_squared();
+    if len_squared < f32::EPSILON {
+        return p.distance(a);
+    }
+    let t = ((p - a).dot(ab) / len_squared).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+// cuts the centerline where it leaves the arena, adding a node on the edge and a
+// gap after it; only the centerline is clipped, the width still reaches out a bit
+fn clip_tail_to_arena(nodes: &[TailNode], arena: &Arena) -> Vec<TailNode> {
+    let mut clipped = Vec::with_capacity(nodes.len());
+    let at = |a: &TailNode, b: &TailNode, t: f32, gap: bool| TailNode {
+        pos: a.pos.lerp(b.pos, t),
+        width: a.width + (b.width - a.width) * t,
+        age: a.age + (b.age - a.age) * t,
+        gap,
+        ..*a
+    };
+    for (i, a) in nodes.iter().enumerate() {
+        let inside = arena.contains(a.pos);
+        if inside {
+            clipped.push(*a);
+        }
+        let b = match nodes.get(i + 1) {
+            Some(b) if !a.gap => b,
+            _ => continue,
+        };
+        if let Some((t0, t1)) = arena.clip_segment(a.pos, b.pos) {
+            if !inside {
+                clipped.push(at(a, b, t0, false));
+            }
+            if !arena.contains(b.pos) {
+                clipped.push(at(a, b, t1, true));
+            }
+        }
+    }
+    clipped
+}
+
+// points one corner may be rounded with, and the most nodes a rounded tail may have;
+// the mesh builders index with u16, every node is 3 vertices flat and about 12 in 3d
+const MAX_CORNER_STEPS: usize = 8;
+const MAX_ROUNDED_NODES: usize = MAX_TAIL_LEN * 4;
+
+// a node turning more than max_angle is replaced by points on a quadratic bezier
+// from the middle of its newer segment to the middle of its older one, enough of
+// them that no step turns more than max_angle, up to MAX_CORNER_STEPS; nodes next
+// to a gap, and every corner once MAX_ROUNDED_NODES is used up, stay sharp
+fn round_tail_corners(nodes: &[TailNode], max_angle: f32) -> Vec<TailNode> {
+    if nodes.len() < 3 || max_angle >= std::f32::consts::PI {
+        return nodes.to_vec();
+    }
+    let max_angle = max_angle.max(0.01);
+    let mut spare = MAX_ROUNDED_NODES.saturating_sub(nodes.len());
+    let mut rounded = Vec::with_capacity(nodes.len());
+    rounded.push(nodes[0]);
+    for i in 1..nodes.len() - 1 {
+        let (newer, node, older) = (nodes[i - 1], nodes[i], nodes[i + 1]);
+        let angle = (newer.pos - node.pos)
+            .angle_between(node.pos - older.pos)
+            .abs();
+        if newer.gap || node.gap || angle.is_nan() || angle <= max_angle || spare == 0 {
+            rounded.push(node);
+            continue;
+        }
+        let (a, c, b) = (
+            newer.pos.lerp(node.pos, 0.5),
+            node.pos,
+            node.pos.lerp(older.pos, 0.5),
+        );
+        let steps = ((angle / max_angle).ceil() as usize + 1)
+            .min(MAX_CORNER_STEPS)
+            .min(spare + 1);
+        spare -= steps - 1;
+        for j in 1..=steps {
+            let t = j as f32 / (steps + 1) as f32;
+            let pos = a * (1. - t) * (1. - t) + c * 2. * (1. - t) * t + b * t * t;
+            // the curve runs from the newer end, movement the other way
+            let tangent = (c - a) * (1. - t) + (b - c) * t;
+            let velocity = safe_normalize(-tangent, Vec2::zero()) * node.velocity.length();
+            rounded.push(TailNode {
+                pos,
+                velocity,
+                ..node
+            });
+        }
+    }
+    rounded.push(nodes[nodes.len() - 1]);
+    rounded
+}
+
+// Ramer-Douglas-Peucker, drops nodes closer than tolerance to the line through the
+// nodes kept around them; the ends, and both sides of a gap, always stay
+fn decimate_tail(nodes: &[TailNode], tolerance: f32) -> Vec<TailNode> {
+    if nodes.len() < 3 || tolerance <= 0. {
+        return nodes.to_vec();
+    }
+    let mut keep = vec![false; nodes.len()];
+    let mut spans = vec![];
+    let mut from = 0;
+    for i in 0..nodes.len() {
+        if nodes[i].gap || i == nodes.len() - 1 {
+            keep[from] = true;
+            keep[i] = true;
+            spans.push((from, i));
+            from = i + 1;
+        }
+    }
+    while let Some((from, to)) = spans.pop() {
+        let (a, b) = (nodes[from].pos, nodes[to].pos);
+        let farthest = (from + 1..to)
+            .map(|i| (i, point_segment_distance(nodes[i].pos, a, b)))
+            // a NaN distance sorts last instead of panicking, and never beats tolerance
+            .max_by(|(_, x), (_, y)| x.total_cmp(y));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                spans.push((from, i));
+                spans.push((i, to));
+            }
+        }
+    }
+    let kept: Vec<usize> = (0..nodes.len()).filter(|&i| keep[i]).collect();
+    // each velocity points along its new, longer segment, with the old speed
+    kept.iter()
+        .enumerate()
+        .map(|(k, &i)| {
+            let node = nodes[i];
+            let velocity = match kept.get(k + 1) {
+                Some(&older) => {
+                    let direction = safe_normalize(node.pos - nodes[older].pos, Vec2::zero());
+                    if direction == Vec2::zero() {
+                        node.velocity
+                    } else {
+                        direction * node.velocity.length()
+                    }
+                }
+                None => node.velocity,
+            };
+            TailNode { velocity, ..node }
+        })
+        .collect()
+}
+
+pub fn make_tail_mesh(mesh: &mut Mesh, tail: &[TailNode]) {
+    let main_tail = centerline(tail);
+    let sub_tail = make_sub_tail(tail, &main_tail, 0.);
+    make_ribbon_mesh(mesh, &main_tail, &sub_tail, &tail_gaps(tail));
+}
+
+// same ribbon grown by stroke_width on both sides
+fn make_outline_mesh(mesh: &mut Mesh, tail: &[TailNode], stroke_width: f32) {
+    let main_tail = centerline(tail);
+    let sub_tail = make_sub_tail(tail, &main_tail, stroke_width);
+    let inner_tail: Vec<Vec2> = main_tail
+        .iter()
+        .zip(tail.iter())
+        .map(|(pos, node)| *pos - get_normal(node.velocity) * stroke_width)
+        .collect();
+    make_ribbon_mesh(mesh, &inner_tail, &sub_tail, &tail_gaps(tail));
+}
+
+#[derive(Clone, Copy)]
+struct RibbonPoint {
+    pos: Vec2,
+    // Vertex_X, across the width
+    x: f32,
+    // Vertex_Y, along the length from head to end
+    along: f32,
+    // Vertex_A
+    alpha: f32,
+    // Vertex_N, index of the node the vertex sits on
+    node: f32,
+}
+
+// depth between head and end of the ribbon, well short of the ghost and outline behind it
+const TAIL_DEPTH_SPREAD: f32 = 0.1;
+
+// every ribbon vertex, laid out for make_tail_indices
+fn make_ribbon_points(main_tail: &[Vec2], sub_tail: &[Vec2]) -> Vec<RibbonPoint> {
+    // a single node has no segment to draw yet
+    if main_tail.len() < 2 {
+        return vec![];
+    }
+    let last = (main_tail.len() - 1) as f32;
+    let mut points = Vec::with_capacity(main_tail.len() + sub_tail.len());
+    for (i, pos) in main_tail.iter().enumerate() {
+        points.push(RibbonPoint {
+            pos: *pos,
+            x: 1.0,
+            along: i as f32 / last,
+            alpha: 1. - ((i as f32) / (main_tail.len() as f32)),
+            node: i as f32,
+        });
+    }
+    for (i, pos) in sub_tail.iter().enumerate() {
+        // sub_tail[2 * n - 1] and sub_tail[2 * n] both sit on node n
+        let node = i.div_ceil(2);
+        points.push(RibbonPoint {
+            pos: *pos,
+            x: 0.0,
+            along: node as f32 / last,
+            alpha: 1. - ((i as f32) / (sub_tail.len() as f32)),
+            node: node as f32,
+        });
+    }
+    points
+}
+
+fn set_ribbon_attributes(mesh: &mut Mesh, points: &[RibbonPoint]) {
+    let colors: Vec<f32> = points.iter().map(|point| point.x).collect();
+    let alongs: Vec<f32> = points.iter().map(|point| point.along).collect();
+    let alphas: Vec<f32> = points.iter().map(|point| point.alpha).collect();
+    let nodes: Vec<f32> = points.iter().map(|point| point.node).collect();
+    debug_assert!(
+        colors
+            .iter()
+            .chain(&alongs)
+            .chain(&alphas)
+            .all(|v| v.is_finite()),
+        "non-finite ribbon attribute"
+    );
+    mesh.set_attribute("Vertex_X", VertexAttributeValues::from(colors));
+    mesh.set_attribute("Vertex_Y", VertexAttributeValues::from(alongs));
+    mesh.set_attribute("Vertex_A", VertexAttributeValues::from(alphas));
+    mesh.set_attribute("Vertex_N", VertexAttributeValues::from(nodes));
+    // nothing decayed until set_node_decay says otherwise
+    mesh.set_attribute(
+        "Vertex_D",
+        VertexAttributeValues::from(vec![0f32; points.len()]),
+    );
+}
+
+// Packed keeps Vertex_X, Vertex_A and Vertex_D of the flat main tail as three
+// unorm8 in one u32, and drops the normals and uvs nothing flat reads, 24 bytes a
+// vertex instead of 52; outlines, ghosts and the 3d ribbon stay Full
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AttributePrecision {
+    Full,
+    Packed,
+}
+
+const PACKED_ATTRIBUTE: &str = "Vertex_P";
+
+fn unorm8(v: f32) -> u32 {
+    (v.clamp(0., 1.) * 255.).round() as u32
+}
+
+// run after everything that writes Vertex_X, a mesh can't drop attributes in
+// place so it's rebuilt
+fn pack_ribbon_attributes(mesh: &mut Mesh) {
+    let floats = |name: &'static str| match mesh.attribute(name) {
+        Some(VertexAttributeValues::Float(values)) => Some(values.clone()),
+        _ => None,
+    };
+    let (colors, alphas, decays, alongs, nodes) = match (
+        floats("Vertex_X"),
+        floats("Vertex_A"),
+        floats("Vertex_D"),
+        floats("Vertex_Y"),
+        floats("Vertex_N"),
+    ) {
+        (Some(x), Some(a), Some(d), Some(y), Some(n)) => (x, a, d, y, n),
+        _ => return,
+    };
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float3(positions)) => positions.clone(),
+        _ => return,
+    };
+    // 0 is left for the negative idle Vertex_X
+    let packed: Vec<u32> = colors
+        .iter()
+        .zip(alphas.iter().zip(decays.iter()))
+        .map(|(&x, (&a, &d))| {
+            let x = if x < 0. {
+                0
+            } else {
+                1 + (x.clamp(0., 1.) * 254.).round() as u32
+            };
+            x | unorm8(a) << 8 | unorm8(d) << 16
+        })
+        .collect();
+    let mut packed_mesh = Mesh::new(mesh.primitive_topology());
+    packed_mesh.set_indices(match mesh.indices() {
+        Some(Indices::U16(indices)) => Some(Indices::U16(indices.clone())),
+        Some(Indices::U32(indices)) => Some(Indices::U32(indices.clone())),
+        None => None,
+    });
+    packed_mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    packed_mesh.set_attribute(PACKED_ATTRIBUTE, VertexAttributeValues::Uint(packed));
+    packed_mesh.set_attribute("Vertex_Y", VertexAttributeValues::from(alongs));
+    packed_mesh.set_attribute("Vertex_N", VertexAttributeValues::from(nodes));
+    *mesh = packed_mesh;
+}
+
+// after update, so anything holding a packed mesh, ghosts swap theirs with the tail
+// and stamps and branches copy its pipelines, draws with the flat pipeline that matches
+fn vertex_precision_system(
+    tail_pipelines: Res<TailPipelines>,
+    meshes: Res<Assets<Mesh>>,
+    mut query: Query<(&Handle<Mesh>, &mut RenderPipelines)>,
+) {
+    for (mesh, mut render_pipelines) in query.iter_mut() {
+        let packed = meshes
+            .get(mesh)
+            .is_some_and(|mesh| mesh.attribute(PACKED_ATTRIBUTE).is_some());
+        let (from, to) = if packed {
+            (&tail_pipelines.flat, &tail_pipelines.packed)
+        } else {
+            (&tail_pipelines.packed, &tail_pipelines.flat)
+        };
+        for pipeline in render_pipelines.pipelines.iter_mut() {
+            if pipeline.pipeline == *from {
+                pipeline.pipeline = to.clone();
+            }
+        }
+    }
+}
+
+// slow nodes, the bunched up ones left while standing still, in their own color
+struct IdleColor {
+    enabled: bool,
+    color: Color,
+    // world units moved since the previous node
+    threshold: f32,
+}
+
+impl Default for IdleColor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Color::rgb(0.5, 0.5, 0.5),
+            threshold: 0.5,
+        }
+    }
+}
+
+// old nodes drift from the palette toward color, the freshest keep the full rainbow
+struct TrailDecay {
+    enabled: bool,
+    color: Color,
+    // 1 - e^(-rate * age) of the way to color
+    rate: f32,
+}
+
+impl Default for TrailDecay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Color::WHITE,
+            rate: 1.,
+        }
+    }
+}
+
+fn tail_age_system(time: Res<Time>, mut query: Query<&mut Player>) {
+    let dt = time.delta_seconds();
+    for mut player in query.iter_mut() {
+        for node in player.tail.iter_mut() {
+            node.age += dt;
+        }
+    }
+}
+
+fn trail_decay_color_system(
+    decay: ChangedRes<TrailDecay>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            let color = decay.color;
+            material.decay_color = Vec4::new(color.r(), color.g(), color.b(), color.a());
+        }
+    }
+}
+
+// Vertex_D per node, like set_node_colors
+fn set_node_decay(mesh: &mut Mesh, tail: &[TailNode], rate: f32) {
+    let decay: Vec<f32> = tail
+        .iter()
+        .map(|node| 1. - (-rate.max(0.) * node.age).exp())
+        .collect();
+    if let Some(per_vertex) = per_node_values(mesh, &decay) {
+        mesh.set_attribute("Vertex_D", VertexAttributeValues::from(per_vertex));
+    }
+}
+
+fn idle_color_system(
+    idle: ChangedRes<IdleColor>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            let color = idle.color;
+            material.idle_color = Vec4::new(color.r(), color.g(), color.b(), color.a());
+        }
+    }
+}
+
+// one color for the whole tail instead of the palette, fades and widths still apply
+#[derive(Default)]
+struct SolidColor(Option<Color>);
+
+fn solid_color_system(
+    solid: ChangedRes<SolidColor>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    let solid_color = match solid.0 {
+        Some(color) => Vec4::new(color.r(), color.g(), color.b(), 1.0),
+        None => Vec4::zero(),
+    };
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.solid_color = solid_color;
+        }
+    }
+}
+
+// how per-node colors blend between two nodes, only visible with a per-node
+// TailColorSource, the width bands don't change along a segment
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+enum ColorInterpolation {
+    #[default]
+    Linear,
+    // hard switch halfway between the nodes
+    Nearest,
+    Smoothstep,
+}
+
+impl ColorInterpolation {
+    fn uniform(self) -> f32 {
+        match self {
+            ColorInterpolation::Linear => 0.,
+            ColorInterpolation::Nearest => 1.,
+            ColorInterpolation::Smoothstep => 2.,
+        }
+    }
+}
+
+fn color_interpolation_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut interpolation: ResMut<ColorInterpolation>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::N) {
+        return;
+    }
+    *interpolation = match *interpolation {
+        ColorInterpolation::Linear => ColorInterpolation::Nearest,
+        ColorInterpolation::Nearest => ColorInterpolation::Smoothstep,
+        ColorInterpolation::Smoothstep => ColorInterpolation::Linear,
+    };
+    info!("color interpolation: {:?}", *interpolation);
+}
+
+fn color_interpolation_system(
+    interpolation: ChangedRes<ColorInterpolation>,
+    mut materials: ResMut<Assets<MyMaterialWithVertexColorSupport>>,
+    query: Query<&Handle<MyMaterialWithVertexColorSupport>, (With<Tail>, Without<Outline>)>,
+) {
+    for handle in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.interp_mode = interpolation.uniform();
+        }
+    }
+}
+
+// writes the negative Vertex_X the fragment shader reads as idle_color
+fn set_idle_nodes(mesh: &mut Mesh, tail: &[TailNode], threshold: f32) {
+    let mut colors = match mesh.attribute("Vertex_X") {
+        Some(VertexAttributeValues::Float(colors)) => colors.clone(),
+        _ => return,
+    };
+    let idle: Vec<bool> = tail
+        .iter()
+        .map(|node| node.velocity.length() < threshold)
+        .collect();
+    let idle = match per_node_values(mesh, &idle) {
+        Some(idle) if idle.len() == colors.len() => idle,
+        _ => return,
+    };
+    for (color, idle) in colors.iter_mut().zip(idle) {
+        if idle {
+            *color = -1.;
+        }
+    }
+    mesh.set_attribute("Vertex_X", VertexAttributeValues::from(colors));
+}
+
+// values[n] on every vertex whose Vertex_N is n, so it fits the flat ribbon and
+// the 3d one alike; None if the mesh wasn't built from values.len() nodes
+fn per_node_values<T: Copy>(mesh: &Mesh, values: &[T]) -> Option<Vec<T>> {
+    if values.len() < 2 {
+        return None;
+    }
+    match mesh.attribute("Vertex_N") {
+        Some(VertexAttributeValues::Float(nodes)) => nodes
+            .iter()
+            .map(|&node| values.get(node as usize).copied())
+            .collect(),
+        _ => None,
+    }
+}
+
+// one color per node instead of the across-width bands
+fn set_node_colors(mesh: &mut Mesh, colors: &[f32]) {
+    let per_vertex = match per_node_values(mesh, colors) {
+        Some(per_vertex) => per_vertex,
+        None => return,
+    };
+    debug_assert!(
+        per_vertex.iter().all(|v| v.is_finite()),
+        "non-finite node color"
+    );
+    mesh.set_attribute("Vertex_X", VertexAttributeValues::from(per_vertex));
+}
+
+fn make_ribbon_mesh(mesh: &mut Mesh, main_tail: &[Vec2], sub_tail: &[Vec2], gaps: &[bool]) {
+    let points = make_ribbon_points(main_tail, sub_tail);
+    let len = if points.is_empty() {
+        0
+    } else {
+        main_tail.len()
+    };
+    // older nodes sit slightly further back, so where the ribbon crosses itself the
+    // newer segment wins the depth test instead of flickering
+    let vertices: Vec<Vertice> = points
+        .iter()
+        .map(|point| {
+            let z = -point.along * TAIL_DEPTH_SPREAD;
+            ([point.pos.x, point.pos.y, z], [0., 0., 1.], [0.; 2])
+        })
+        .collect();
+    let indices = make_tail_indices(len, gaps);
+    modify_mesh(mesh, &vertices, indices);
+    set_ribbon_attributes(mesh, &points);
+}
+
+// andrew's monotone chain, counterclockwise without repeating the first point
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| {
+        a.x.partial_cmp(&b.x)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.y.partial_cmp(&b.y).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let mut lower = half_hull(sorted.iter().copied());
+    let mut upper = half_hull(sorted.iter().rev().copied());
+    // each chain ends where the other one starts
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+fn half_hull(points: impl Iterator<Item = Vec2>) -> Vec<Vec2> {
+    let mut hull: Vec<Vec2> = vec![];
+    for point in points {
+        while hull.len() >= 2 {
+            let (o, a) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+            if (a - o).perp_dot(point - o) > 0. {
+                break;
+            }
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull
+}
+
+// what make_tail_mesh builds its triangles from
+fn ribbon_vertices(tail: &[TailNode]) -> Vec<Vec2> {
+    let main_tail = centerline(tail);
+    let sub_tail = make_sub_tail(tail, &main_tail, 0.);
+    main_tail.into_iter().chain(sub_tail).collect()
+}
+
+// closed line strip around the hull
+fn make_hull_mesh(mesh: &mut Mesh, hull: &[Vec2]) {
+    if hull.len() < 2 {
+        clear_line_mesh(mesh);
+        return;
+    }
+    let points: Vec<RibbonPoint> = hull
+        .iter()
+        .chain(hull.first())
+        .map(|pos| RibbonPoint {
+            pos: *pos,
+            x: 1.0,
+            along: 0.,
+            alpha: 1.,
+            node: 0.,
+        })
+        .collect();
+    let vertices: Vec<Vertice> = points
+        .iter()
+        .map(|point| (vec2_to_array_3(point.pos), [0., 0., 1.], [0.; 2]))
+        .collect();
+    let indices = (0..points.len() as u16).collect();
+    modify_mesh(mesh, &vertices, indices);
+    set_ribbon_attributes(mesh, &points);
+}
+
+// no points, but every attribute the tail pipeline reads, so it still compiles for
+// the entity if it's drawn
+fn clear_line_mesh(mesh: &mut Mesh) {
+    modify_mesh(mesh, &[], vec![]);
+    set_ribbon_attributes(mesh, &[]);
+}
+
+// line strip from head to head + offset, fading out toward the far end
+fn make_prediction_mesh(mesh: &mut Mesh, head: Vec2, offset: Vec2) {
+    let points: Vec<RibbonPoint> = (0..=PREDICTION_SEGMENTS)
+        .map(|i| {
+            let along = i as f32 / PREDICTION_SEGMENTS as f32;
+            RibbonPoint {
+                pos: head + offset * along,
+                x: 0.5,
+                along,
+                alpha: 1. - along,
+                node: i as f32,
+            }
+        })
+        .collect();
+    let vertices: Vec<Vertice> = points
+        .iter()
+        .map(|point| (vec2_to_array_3(point.pos), [0., 0., 1.], [0.; 2]))
+        .collect();
+    let indices = (0..points.len() as u16).collect();
+    modify_mesh(mesh, &vertices, indices);
+    set_ribbon_attributes(mesh, &points);
+}
+
+fn make_tail_mesh_3d(mesh: &mut Mesh, tail: &[TailNode], depth: f32) {
+    let main_tail = centerline(tail);
+    let sub_tail = make_sub_tail(tail, &main_tail, 0.);
+    let points = make_ribbon_points(&main_tail, &sub_tail);
+    let len = if points.is_empty() {
+        0
+    } else {
+        main_tail.len()
+    };
+    let gaps = tail_gaps(tail);
+    let flat_indices = make_tail_indices(len, &gaps);
+
+    let mut vertices: Vec<Vertice> = vec![];
+    let mut vertex_points = vec![];
+    let mut indices: Vec<u16> = vec![];
+
+    // front and back faces are the flat ribbon at +-depth / 2
+    for &(z, normal_z) in &[(depth / 2., 1.), (-depth / 2., -1.)] {
+        let base = vertices.len() as u16;
+        for point in points.iter() {
+            vertices.push(([point.pos.x, point.pos.y, z], [0., 0., normal_z], [0., 0.]));
+            vertex_points.push(*point);
+        }
+        indices.extend(flat_indices.iter().map(|i| i + base));
+    }
+
+    // side walls along the centerline, the outer edge and both caps, plus caps on
+    // either side of every gap
+    let mut boundary = vec![];
+    if len >= 2 {
+        let sub_len = sub_tail.len();
+        let gap = |i: usize| gaps[i];
+        for i in 0..len - 1 {
+            if !gap(i) {
+                boundary.push((i, i + 1));
+            }
+        }
+        for i in 0..sub_len - 1 {
+            // even pairs run along segment i / 2, odd ones are the wedge at a node
+            let node = i.div_ceil(2);
+            if gap(i / 2) || (i % 2 == 1 && gap(node - 1)) {
+                continue;
+            }
+            boundary.push((len + i, len + i + 1));
+        }
+        if !gap(0) {
+            boundary.push((0, len));
+        }
+        if !gap(len - 2) {
+            boundary.push((len - 1, len + sub_len - 1));
+        }
+        for i in 0..len - 1 {
+            if !gap(i) {
+                continue;
+            }
+            if i > 0 && !gap(i - 1) {
+                boundary.push((i, len + 2 * i - 1));
+            }
+            if i + 2 < len && !gap(i + 1) {
+                boundary.push((i + 1, len + 2 * i + 2));
+            }
+        }
+    }
+    for (a, b) in boundary {
+        let (point_a, point_b) = (points[a], points[b]);
+        let normal = get_normal(point_b.pos - point_a.pos);
+        let normal = [normal.x, normal.y, 0.];
+        let base = vertices.len() as u16;
+        for &(point, z) in &[
+            (point_a, depth / 2.),
+            (point_b, depth / 2.),
+            (point_b, -depth / 2.),
+            (point_a, -depth / 2.),
+        ] {
+            vertices.push(([point.pos.x, point.pos.y, z], normal, [0., 0.]));
+            vertex_points.push(point);
+        }
+        indices.extend(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    modify_mesh(mesh, &vertices, indices);
+    set_ribbon_attributes(mesh, &vertex_points);
+}
+
+pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--export-palette") {
+        let path = args.get(i + 1).map(String::as_str).unwrap_or("palette.png");
+        match palette::export_palette_png(Path::new(path)) {
+            Ok(()) => println!("palette written to {}", path),
+            Err(err) => println!("failed to write palette to {}: {}", path, err),
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--snapshot-tail") {
+        match args.get(i + 1) {
+            Some(path) if snapshot::run_snapshot_check(Path::new(path)) => return,
+            Some(_) => std::process::exit(1),
+            None => {
+                println!("--snapshot-tail needs a path");
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut startup_palette = StartupPalette(None);
+    if let Some(i) = args.iter().position(|arg| arg == "--palette-image") {
+        match args.get(i + 1) {
+            Some(path) => match palette::palette_from_image(Path::new(path), IMAGE_PALETTE_SAMPLES)
+            {
+                Ok(stops) => startup_palette.0 = Some(stops),
+                Err(err) => println!("failed to read palette from {}: {}", path, err),
+            },
+            None => println!("--palette-image needs a path"),
+        }
+    }
+    let mut tail_shaders = TailShaders::default();
+    if args.iter().any(|arg| arg == "--wobble") {
+        tail_shaders.vertex = Some(WOBBLE_VERTEX_SHADER.to_string());
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--vertex-shader") {
+        match args.get(i + 1).map(std::fs::read_to_string) {
+            Some(Ok(src)) => tail_shaders.vertex = Some(src),
+            Some(Err(err)) => println!("failed to read vertex shader: {}", err),
+            None => println!("--vertex-shader needs a path"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--fragment-shader") {
+        match args.get(i + 1).map(std::fs::read_to_string) {
+            Some(Ok(src)) => tail_shaders.fragment = Some(src),
+            Some(Err(err)) => println!("failed to read fragment shader: {}", err),
+            None => println!("--fragment-shader needs a path"),
+        }
+    }
+    let hot_reload = ShaderHotReload(args.iter().any(|arg| arg == "--hot-reload"));
+    let mut length_cycles = LengthCycles(0.);
+    if let Some(i) = args.iter().position(|arg| arg == "--length-cycles") {
+        match args.get(i + 1).map(|cycles| cycles.parse::<f32>()) {
+            Some(Ok(cycles)) => length_cycles.0 = cycles.max(0.),
+            _ => println!("--length-cycles needs a number"),
+        }
+    }
+    let mut player_mesh = PlayerMesh::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--player-mesh") {
+        match args.get(i + 1) {
+            Some(path) => player_mesh.0 = Some(path.clone()),
+            None => println!("--player-mesh needs an asset path"),
+        }
+    }
+    let mut tail_capture = capture::TailCapture::default();
+    if args.iter().any(|arg| arg == "--capture") {
+        tail_capture.enabled = true;
+        tail_capture.preview = true;
+    }
+    let mut screenshot = screenshot::Screenshot::default();
+    if args.iter().any(|arg| arg == "--screenshots") {
+        screenshot.enabled = true;
+        tail_capture.enabled = true;
+    }
+    let mut gif_recorder = gif::GifRecorder::default();
+    if args.iter().any(|arg| arg == "--gif") {
+        gif_recorder.enabled = true;
+        tail_capture.enabled = true;
+    }
+    let mut tail_blur = blur::TailBlur { radius: 0. };
+    if let Some(i) = args.iter().position(|arg| arg == "--blur") {
+        match args.get(i + 1).map(|radius| radius.parse::<f32>()) {
+            Some(Ok(radius)) => {
+                tail_blur.radius = radius;
+                tail_capture.enabled = true;
+            }
+            _ => println!("--blur needs a radius in pixels"),
+        }
+    }
+    let mut tail_mask = mask::TailMask::default();
+    tail_mask.enabled = args.iter().any(|arg| arg == "--mask");
+    let mut curvature = CurvatureLimit::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--max-curvature") {
+        match args.get(i + 1).map(|degrees| degrees.parse::<f32>()) {
+            Some(Ok(degrees)) => curvature.max_angle = degrees.to_radians(),
+            _ => println!("--max-curvature needs an angle in degrees"),
+        }
+    }
+    let mut speed_width = SpeedWidth {
+        enabled: args.iter().any(|arg| arg == "--speed-width"),
+        ..Default::default()
+    };
+    for (flag, curve) in &mut [
+        ("--width-curve", &mut speed_width.width_curve),
+        ("--color-curve", &mut speed_width.color_curve),
+    ] {
+        if let Some(i) = args.iter().position(|arg| arg == flag) {
+            match args
+                .get(i + 1)
+                .and_then(|name| ResponseCurve::from_arg(name))
+            {
+                Some(parsed) => **curve = parsed,
+                None => println!("{} needs linear, log, exp or smoothstep", flag),
+            }
+        }
+    }
+    let mut orphans = OrphanTailPolicy::Freeze;
+    if let Some(i) = args.iter().position(|arg| arg == "--orphan-tails") {
+        match args.get(i + 1).map(String::as_str) {
+            Some("hide") => orphans = OrphanTailPolicy::Hide,
+            Some("freeze") => orphans = OrphanTailPolicy::Freeze,
+            Some("despawn") => orphans = OrphanTailPolicy::Despawn,
+            _ => println!("--orphan-tails needs hide, freeze or despawn"),
+        }
+    }
+    let mut label_config = labels::LabelConfig {
+        enabled: args.iter().any(|arg| arg == "--labels"),
+        ..Default::default()
+    };
+    if let Some(i) = args.iter().position(|arg| arg == "--player-name") {
+        match args.get(i + 1) {
+            Some(name) => label_config.player_name = name.clone(),
+            None => println!("--player-name needs a name"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--label-font") {
+        match args.get(i + 1) {
+            Some(path) => label_config.font = path.clone(),
+            None => println!("--label-font needs an asset path"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--label-size") {
+        match args.get(i + 1).map(|size| size.parse::<f32>()) {
+            Some(Ok(size)) => label_config.size = size.max(1.),
+            _ => println!("--label-size needs a size in pixels"),
+        }
+    }
+    // no font ships with the repo
+    if label_config.enabled && !asset_root().join(&label_config.font).exists() {
+        println!(
+            "label font {} not found under {}, labels stay blank",
+            label_config.font,
+            asset_root().display()
+        );
+    }
+    let lasso = lasso::Lasso {
+        enabled: args.iter().any(|arg| arg == "--lasso"),
+        ..Default::default()
+    };
+    let mut initial_position = InitialPosition::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--initial-position") {
+        let parsed = args.get(i + 1).and_then(|pos| {
+            let mut parts = pos.split(',').map(|part| part.trim().parse::<f32>());
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => Some(Vec2::new(x, y)),
+                _ => None,
+            }
+        });
+        match parsed {
+            Some(pos) => initial_position.0 = pos,
+            None => println!("--initial-position needs x,y"),
+        }
+    }
+    let focus_dim = FocusDim {
+        enabled: args.iter().any(|arg| arg == "--focus-dim"),
+        ..Default::default()
+    };
+    let mut precision = if args.iter().any(|arg| arg == "--packed-attributes") {
+        AttributePrecision::Packed
+    } else {
+        AttributePrecision::Full
+    };
+    // the masked pipeline reads the full attributes
+    if tail_mask.enabled && precision == AttributePrecision::Packed {
+        println!("--packed-attributes doesn't work with --mask, keeping full precision");
+        precision = AttributePrecision::Full;
+    }
+    let mut seed = None;
+    if let Some(i) = args.iter().position(|arg| arg == "--seed") {
+        match args.get(i + 1).map(|seed| seed.parse::<u64>()) {
+            Some(Ok(value)) => seed = Some(value),
+            _ => println!("--seed needs a whole number"),
+        }
+    }
+    let tail_collision = TailCollision {
+        enabled: args.iter().any(|arg| arg == "--tail-collision"),
+    };
+    let mut tail_grid = TailGrid::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--grid") {
+        match args.get(i + 1).map(|size| size.parse::<f32>()) {
+            Some(Ok(size)) => tail_grid.cell_size = size.max(0.),
+            _ => println!("--grid needs a cell size"),
+        }
+    }
+    let mut stroke = TailStroke::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--stroke") {
+        match args.get(i + 1).map(|width| width.parse::<f32>()) {
+            Some(Ok(width)) => stroke.width = width.max(0.),
+            _ => println!("--stroke needs a width"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--stroke-color") {
+        match args.get(i + 1).map(Color::hex) {
+            Some(Ok(color)) => stroke.color = color,
+            _ => println!("--stroke-color needs a hex color like 000000"),
+        }
+    }
+    let render_interpolation = RenderInterpolation(args.iter().any(|arg| arg == "--interpolate"));
+    // a steady beat to try the response without an audio analysis feeding it
+    let mut beat = BeatStrength(0.);
+    if let Some(i) = args.iter().position(|arg| arg == "--beat") {
+        match args.get(i + 1).map(|strength| strength.parse::<f32>()) {
+            Some(Ok(strength)) => beat.0 = strength.max(0.),
+            _ => println!("--beat needs a strength"),
+        }
+    }
+    let mut smoothing = TailSmoothing {
+        window: 1,
+        normal_window: 1,
+    };
+    if let Some(i) = args.iter().position(|arg| arg == "--smooth") {
+        match args.get(i + 1).map(|window| window.parse::<usize>()) {
+            Some(Ok(window)) => smoothing.window = window.max(1),
+            _ => println!("--smooth needs a window in nodes"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--smooth-normals") {
+        match args.get(i + 1).map(|window| window.parse::<usize>()) {
+            Some(Ok(window)) => smoothing.normal_window = window.max(1),
+            _ => println!("--smooth-normals needs a window in nodes"),
+        }
+    }
+    let mut mouse_prediction = MousePrediction { time: 0. };
+    if let Some(i) = args.iter().position(|arg| arg == "--mouse-prediction") {
+        match args.get(i + 1).map(|time| time.parse::<f32>()) {
+            Some(Ok(time)) => mouse_prediction.time = time.max(0.),
+            _ => println!("--mouse-prediction needs a time in seconds"),
+        }
+    }
+    let idle_retract = IdleRetract {
+        enabled: args.iter().any(|arg| arg == "--idle-retract"),
+        ..Default::default()
+    };
+    let mut trajectory = TrajectoryPrediction {
+        enabled: args.iter().any(|arg| arg == "--trajectory"),
+        ..Default::default()
+    };
+    if let Some(i) = args.iter().position(|arg| arg == "--trajectory-horizon") {
+        match args.get(i + 1).map(|horizon| horizon.parse::<f32>()) {
+            Some(Ok(horizon)) => trajectory.horizon = horizon.max(0.),
+            _ => println!("--trajectory-horizon needs a time in seconds"),
+        }
+    }
+    let mut dead_zone = DeadZone::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--dead-zone") {
+        match args.get(i + 1).map(|radius| radius.parse::<f32>()) {
+            Some(Ok(radius)) => dead_zone.radius = radius.max(0.),
+            _ => println!("--dead-zone needs a radius"),
+        }
+    }
+    let mut motion_stretch = MotionStretch {
+        enabled: args.iter().any(|arg| arg == "--motion-stretch"),
+        ..Default::default()
+    };
+    if let Some(i) = args.iter().position(|arg| arg == "--max-stretch") {
+        match args.get(i + 1).map(|stretch| stretch.parse::<f32>()) {
+            Some(Ok(stretch)) => motion_stretch.max_stretch = stretch.max(1.),
+            _ => println!("--max-stretch needs a length over width"),
+        }
+    }
+    let mut idle_color = IdleColor::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--idle-color") {
+        match args.get(i + 1).map(Color::hex) {
+            Some(Ok(color)) => {
+                idle_color.enabled = true;
+                idle_color.color = color;
+            }
+            _ => println!("--idle-color needs a hex color like 808080"),
+        }
+    }
+    let mut startup_grow = StartupGrow::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--startup-grow") {
+        match args.get(i + 1).map(|duration| duration.parse::<f32>()) {
+            Some(Ok(duration)) => startup_grow.duration = duration.max(0.),
+            _ => println!("--startup-grow needs a duration in seconds"),
+        }
+    }
+    let shockwave = shockwave::Shockwave {
+        enabled: args.iter().any(|arg| arg == "--shockwave"),
+        ..Default::default()
+    };
+    let mut trail_decay = TrailDecay::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--trail-decay") {
+        match args.get(i + 1).map(Color::hex) {
+            Some(Ok(color)) => {
+                trail_decay.enabled = true;
+                trail_decay.color = color;
+            }
+            _ => println!("--trail-decay needs a hex color like ffffff"),
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--trail-decay-rate") {
+        match args.get(i + 1).map(|rate| rate.parse::<f32>()) {
+            Some(Ok(rate)) => trail_decay.rate = rate.max(0.),
+            _ => println!("--trail-decay-rate needs a rate per second"),
+        }
+    }
+    let mut solid_color = SolidColor::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--solid-color") {
+        match args.get(i + 1).map(Color::hex) {
+            Some(Ok(color)) => solid_color.0 = Some(color),
+            _ => println!("--solid-color needs a hex color like ff8800"),
+        }
+    }
+    let mut decimation = TailDecimation::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--decimate") {
+        match args.get(i + 1).map(|tolerance| tolerance.parse::<f32>()) {
+            Some(Ok(tolerance)) => decimation.tolerance = tolerance.max(0.),
+            _ => println!("--decimate needs a tolerance in world units"),
+        }
+    }
+    let mut screen_space_width = ScreenSpaceWidth::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--screen-width") {
+        match args.get(i + 1).map(|pixels| pixels.parse::<f32>()) {
+            Some(Ok(pixels)) => screen_space_width.0 = pixels.max(0.),
+            _ => println!("--screen-width needs a width in pixels"),
+        }
+    }
+    let mut player_shape = PlayerShape::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--corner-radius") {
+        match args.get(i + 1).map(|radius| radius.parse::<f32>()) {
+            Some(Ok(radius)) => player_shape.corner_radius = radius.max(0.),
+            _ => println!("--corner-radius needs a radius"),
+        }
+    }
+    let idle_breathing = IdleBreathing {
+        enabled: args.iter().any(|arg| arg == "--idle-breathing"),
+        ..Default::default()
+    };
+
+    App::build()
+        .add_plugins(DefaultPlugins)
+        .add_asset::<MyMaterialWithVertexColorSupport>()
+        .add_asset::<blur::BlurMaterial>()
+        .add_asset::<mask::TailMaskMaterial>()
+        .add_resource(MousePos(initial_position.0))
+        .add_resource(initial_position)
+        .add_resource(TailTimer(Timer::new(Duration::from_millis(10u64), true)))
+        .add_resource(TailFade::default())
+        .add_resource(focus_dim)
+        .add_resource(WindowUnfocused::default())
+        .add_resource(HitchGuard::default())
+        .add_resource(tail_grid)
+        .add_resource(ReplaySpeed(1.0))
+        .add_resource(stroke)
+        .add_resource(Extrude3d::default())
+        .add_resource(startup_palette)
+        .add_resource(tail_shaders)
+        .add_resource(tail_capture)
+        .add_resource(capture::CaptureReadback::default())
+        .add_resource(tail_blur)
+        .add_resource(length_cycles)
+        .add_resource(hot_reload)
+        .add_resource(RngResource::new(seed))
+        .add_resource(render_interpolation)
+        .add_resource(Arena {
+            clip_tail: args.iter().any(|arg| arg == "--clip-arena"),
+            ..Default::default()
+        })
+        .add_resource(speed_width)
+        .add_resource(smoothing)
+        .add_resource(CameraShake::default())
+        .add_resource(CameraFollow::default())
+        .add_resource(PaletteTransition { duration: 0.5 })
+        .add_resource(beat)
+        .add_resource(BeatResponse::default())
+        .add_resource(WidthEasing { rate: 8.0 })
+        .add_resource(minimap::Minimap {
+            enabled: args.iter().any(|arg| arg == "--minimap"),
+            ..Default::default()
+        })
+        .add_resource(tail_collision)
+        .add_resource(SpawnGrace::default())
+        .add_event::<TailNodePushed>()
+        .add_event::<CrossedTailEvent>()
+        .add_event::<ConsumeTailEvent>()
+        .add_event::<PaletteSwapEvent>()
+        .add_event::<TailRemeshed>()
+        .add_event::<lasso::LassoClosedEvent>()
+        .add_event::<ReachedTargetEvent>()
+        .add_event::<capture::CaptureFrame>()
+        .add_resource(TargetMovement::default())
+        .add_resource(StampMeshCache::default())
+        .add_resource(GhostTail::default())
+        .add_resource(MouseVelocity::default())
+        .add_resource(mouse_prediction)
+        .add_resource(idle_retract)
+        .add_resource(dead_zone)
+        .add_resource(WorldOrigin::default())
+        .add_resource(PointerLock::default())
+        .add_resource(startup_grow)
+        .add_resource(TailLayers::default())
+        .add_resource(Rewind { rate: 120. })
+        .add_resource(MaxPlayers(4))
+        .add_resource(player_mesh)
+        .add_resource(PlayerCount::default())
+        .add_resource(trajectory)
+        .add_resource(HullDebug::default())
+        .add_resource(motion_stretch)
+        .add_resource(idle_breathing)
+        .add_resource(lasso)
+        .add_resource(if args.iter().any(|arg| arg == "--ribbon-cursor") {
+            TailFollow::Mouse
+        } else {
+            TailFollow::Player
+        })
+        .add_resource(screenshot)
+        .add_resource(idle_color)
+        .add_resource(solid_color)
+        .add_resource(trail_decay)
+        .add_resource(ColorInterpolation::default())
+        .add_resource(SpeedBoost::default())
+        .add_resource(CenterPlayer::default())
+        .add_resource(decimation)
+        .add_resource(curvature)
+        .add_resource(orphans)
+        .add_resource(precision)
+        .add_resource(player_shape)
+        .add_resource(if args.iter().any(|arg| arg == "--even-spacing") {
+            TailPushCap {
+                max_per_frame: EVEN_SPACING_MAX_PUSHES,
+                interpolate: true,
+            }
+        } else {
+            TailPushCap::default()
+        })
+        .add_resource(screen_space_width)
+        .add_resource(BezierPlayback::default())
+        .add_resource(TailColorSource::Width)
+        .add_resource(BranchConfig { angle: 0.5 })
+        .add_resource(sprites::TailStyle::default())
+        .add_resource(markers::DistanceMarkers::default())
+        .add_resource(gif_recorder)
+        .add_resource(shockwave)
+        .add_resource(label_config)
+        .add_resource(timer_bar::TimerBar::default())
+        .add_resource(audio::TailAudioConfig::default())
+        .add_resource(tail_mask)
+        .add_startup_system(setup.system())
+        .add_startup_system(minimap::setup_minimap.system())
+        .add_startup_system(sprites::setup_tail_sprites.system())
+        .add_startup_system(markers::setup_distance_markers.system())
+        .add_startup_system(shockwave::setup_shockwave.system())
+        .add_startup_system(timer_bar::setup_timer_bar.system())
+        .add_startup_system(capture::setup_capture.system())
+        .add_startup_system(blur::setup_blur.system())
+        .add_startup_system(mask::setup_mask.system())
+        .add_startup_system(startup_palette_system.system())
+        .add_system(mouse_movement_updating_system.system())
+        .add_system(pointer_lock_system.system())
+        .add_system(mouse_velocity_system.system())
+        .add_system(input_source_cycle_system.system())
+        .add_system(move_system.system())
+        .add_system(move_target_input_system.system())
+        .add_system(move_target_system.system())
+        .add_system(reached_target_report_system.system())
+        .add_system(bezier_input_system.system())
+        .add_system(bezier_path_system.system())
+        .add_system(tail_gen_system.system())
+        .add_system(interpolation_system.system())
+        .add_system(tail_width_system.system())
+        .add_system(velocity_system.system())
+        .add_system(prediction_system.system())
+        .add_system(player_color_system.system())
+        .add_system(beat_intensity_system.system())
+        .add_system(camera_shake_system.system())
+        .add_system(tail_save_system.system())
+        .add_system(state_save_system.system())
+        .add_system(replay_system.system())
+        .add_system(tail_length_input_system.system())
+        .add_system(boost_input_system.system())
+        .add_system(speed_boost_system.system())
+        .add_system(consume_tail_system.system())
+        .add_system(ghost_tail_system.system())
+        .add_system(tail_color_source_system.system())
+        // before tail_system, which packs by the pipeline it finds
+        .add_system(render_dimension_input_system.system())
+        .add_system(render_dimension_system.system())
+        .add_system(tail_system.system())
+        .add_system(orphan_tail_system.system())
+        .add_system(stamp_cache_system.system())
+        .add_system(stamp_system.system())
+        .add_system(fork_input_system.system())
+        .add_system(rewind_input_system.system())
+        .add_system(rewind_system.system())
+        .add_system(player_count_system.system())
+        .add_system(camera_follow_input_system.system())
+        .add_system(camera_follow_system.system())
+        .add_system(hull_input_system.system())
+        .add_system(hull_system.system())
+        .add_system(motion_stretch_system.system())
+        .add_system(tail_follow_system.system())
+        .add_system(idle_breathing_system.system())
+        .add_system(idle_color_system.system())
+        .add_system(solid_color_system.system())
+        .add_system(tail_age_system.system())
+        .add_system(trail_decay_color_system.system())
+        .add_system(color_interpolation_input_system.system())
+        .add_system(color_interpolation_system.system())
+        .add_system(branch_tail_system.system())
+        .add_system(sprites::tail_style_input_system.system())
+        .add_system(sprites::tail_sprite_system.system())
+        .add_system(markers::distance_marker_input_system.system())
+        .add_system(markers::distance_marker_system.system())
+        .add_system(gif::gif_record_system.system())
+        .add_system(screenshot::screenshot_system.system())
+        .add_system(shockwave::shockwave_spawn_system.system())
+        .add_system(shockwave::shockwave_ring_system.system())
+        .add_system(labels::player_label_system.system())
+        .add_system(lasso::lasso_system.system())
+        .add_system(timer_bar::timer_bar_input_system.system())
+        .add_system(timer_bar::timer_bar_system.system())
+        .add_system(audio::tail_audio_system.system())
+        .add_system(mask::tail_mask_system.system())
+        .add_system(capture::capture_marker_system.system())
+        .add_system(focus_dim_system.system())
+        .add_system(tail_fade_system.system())
+        .add_system(palette_cycle_system.system())
+        .add_system(palette_swap_system.system())
+        .add_system(minimap::minimap_system.system())
+        .add_system(spawn_time_system.system())
+        .add_system_to_stage(stage::POST_UPDATE, vertex_precision_system.system())
+        .add_system_to_stage(stage::PRE_UPDATE, capture::capture_readback_system.system())
+        .add_system(spawn_grace_blink_system.system())
+        .add_system(tail_collision_system.system())
+        .add_system(crossed_tail_report_system.system())
+        .run();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::Stage;
+
+    fn zig_zag(len: usize) -> Vec<TailNode> {
+        (0..len)
+            .map(|i| TailNode {
+                pos: Vec2::new(i as f32 * 10., if i % 2 == 0 { 0. } else { 40. }),
+                velocity: Vec2::new(1., 0.),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn asset_app() -> AppBuilder {
+        let mut app = App::build();
+        app.add_plugin(bevy::reflect::ReflectPlugin)
+            .add_plugin(bevy::core::CorePlugin)
+            .add_plugin(bevy::asset::AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<MyMaterialWithVertexColorSupport>();
+        app
+    }
+
+    fn vertex_count(mesh: &Mesh) -> usize {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => positions.len(),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn round_tail_corners_rounds_a_sharp_corner() {
+        let nodes = zig_zag(3);
+        let rounded = round_tail_corners(&nodes, 0.5);
+        assert!(rounded.len() > nodes.len());
+        assert_eq!(rounded[0].pos, nodes[0].pos);
+        assert_eq!(rounded[rounded.len() - 1].pos, nodes[2].pos);
+        assert!(rounded.iter().all(|node| node.pos.is_finite()));
+    }
+
+    #[test]
+    fn round_tail_corners_stays_inside_u16_indices() {
+        let nodes = zig_zag(MAX_TAIL_LEN);
+        let rounded = round_tail_corners(&nodes, 1f32.to_radians());
+        assert!(rounded.len() <= MAX_ROUNDED_NODES);
+
+        let mut mesh = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut mesh, &rounded);
+        assert!(vertex_count(&mesh) <= u16::MAX as usize);
+        make_tail_mesh_3d(&mut mesh, &rounded, 20.);
+        assert!(vertex_count(&mesh) <= u16::MAX as usize);
+    }
+
+    #[test]
+    fn tails_get_their_own_material() {
+        let mut app = asset_app();
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        {
+            let mut meshes = resources.get_mut::<Assets<Mesh>>().unwrap();
+            let mut materials = resources
+                .get_mut::<Assets<MyMaterialWithVertexColorSupport>>()
+                .unwrap();
+            let mut count = PlayerCount::default();
+            let max = MaxPlayers(2);
+            for _ in 0..2 {
+                let player = spawn_player(
+                    &mut commands,
+                    &mut count,
+                    &max,
+                    Player::full(4),
+                    None,
+                    &mut meshes,
+                    Handle::default(),
+                    PlayerColor(Color::WHITE),
+                    Vec3::zero(),
+                )
+                .unwrap();
+                attach_tail(
+                    &mut commands,
+                    &mut count,
+                    &max,
+                    player,
+                    MeshBundle::default(),
+                    MyMaterialWithVertexColorSupport::default(),
+                    &mut materials,
+                )
+                .unwrap();
+            }
+        }
+        commands.apply(world, resources);
+
+        let handles: Vec<_> = world
+            .query::<(&Tail, &Handle<MyMaterialWithVertexColorSupport>)>()
+            .map(|(_, handle)| handle.clone())
+            .collect();
+        assert_eq!(handles.len(), 2);
+        assert_ne!(handles[0], handles[1]);
+        let mut materials = resources
+            .get_mut::<Assets<MyMaterialWithVertexColorSupport>>()
+            .unwrap();
+        set_global_alpha(&mut materials, &handles[0], 0.25);
+        materials.get_mut(&handles[0]).unwrap().palette[0] = Vec4::zero();
+        let other = materials.get(&handles[1]).unwrap();
+        assert_eq!(other.global_alpha, 1.0);
+        assert_eq!(other.palette, palette::rainbow_stops().to_vec());
+    }
+
+    fn debug_player() -> Player {
+        let mut player = Player::full(0);
+        player.make_debug_tail(Vec2::new(100., -50.));
+        player
+    }
+
+    #[test]
+    fn tail_centroid_of_the_debug_tail() {
+        let player = debug_player();
+        assert_eq!(player.tail_centroid(), Vec2::new(100. - 250., -50. + 150.));
+    }
+
+    #[test]
+    fn tail_centroid_of_an_empty_tail_is_the_player() {
+        let mut player = Player::full(0);
+        assert_eq!(player.tail_centroid(), Vec2::zero());
+        player.set_tail_len(MIN_TAIL_LEN);
+        player.push_tail_node(Vec2::new(10., 20.));
+        player.push_tail_node(Vec2::new(15., 20.));
+        player.consume_tail(2);
+        assert!(player.tail.is_empty());
+        assert_eq!(player.tail_centroid(), Vec2::new(15., 20.));
+    }
+
+    #[test]
+    fn segments_of_a_filled_tail() {
+        let mut player = Player::full(16);
+        assert_eq!(player.segments().count(), player.tail.len() - 1);
+        let (a, b, width) = player.segments().next().unwrap();
+        assert_eq!(
+            (a, b, width),
+            (player.tail[0].pos, player.tail[1].pos, player.tail[0].width)
+        );
+
+        // a teleport drops the segment across the jump
+        player.push_tail_node(player.position + Vec2::splat(TELEPORT_DISTANCE * 2.));
+        assert_eq!(player.segments().count(), player.tail.len() - 2);
+    }
+
+    fn triangle_areas(mesh: &Mesh) -> Vec<f32> {
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => positions.clone(),
+            _ => vec![],
+        };
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|&i| i as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|&i| i as usize).collect(),
+            None => vec![],
+        };
+        indices
+            .chunks(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|k| Vec3::from(positions[triangle[k]]).truncate());
+                ((b - a).perp_dot(c - a) / 2.).abs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reverse_tail_keeps_a_valid_ribbon() {
+        let mut player = Player::full(32);
+        let mut centerline = player.centerline();
+        player.reverse_tail();
+        centerline.reverse();
+        assert_eq!(player.centerline(), centerline);
+
+        let mut mesh = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut mesh, &player.tail);
+        let areas = triangle_areas(&mesh);
+        assert!(!areas.is_empty());
+        assert!(areas.iter().all(|&area| area > 1e-3), "{:?}", areas);
+    }
+
+    #[test]
+    fn tail_aabb_of_the_debug_tail() {
+        let player = debug_player();
+        assert_eq!(
+            player.tail_aabb(),
+            Some((Vec2::new(100. - 400., -50.), Vec2::new(100., -50. + 400.)))
+        );
+        assert_eq!(Player::full(0).tail_aabb(), None);
+    }
+
+    #[test]
+    fn follow_target_frames_the_tail_near_the_player() {
+        let player = debug_player();
+        // the box is 400 across, its middle is (-200, 200) off the head
+        let head = Vec2::new(100., -50.);
+        let target = follow_target(head, &player);
+        assert!((target.distance(head) - FOLLOW_TAIL_PULL).abs() < 1e-3);
+        assert!((target - head)
+            .normalize()
+            .abs_diff_eq(Vec2::new(-1., 1.).normalize(), 1e-5));
+        assert_eq!(follow_target(head, &Player::full(0)), head);
+    }
+
+    #[test]
+    fn coincident_nodes_give_a_finite_mesh() {
+        let mut player = Player::full(0);
+        player.set_tail_len(16);
+        for _ in 0..8 {
+            player.push_tail_node(Vec2::new(3., 4.));
+        }
+        player.push_tail_node(Vec2::new(3.5, 4.));
+        assert!(!player.push_tail_node(Vec2::new(f32::NAN, 0.)));
+        player.push_node(TailNode {
+            pos: Vec2::new(3.5, 4.),
+            velocity: Vec2::zero(),
+            width: 0.,
+            ..Default::default()
+        });
+
+        let mut flat = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut flat, &player.tail);
+        let mut extruded = make_mesh(&[], vec![]);
+        make_tail_mesh_3d(&mut extruded, &player.tail, 20.);
+        for mesh in &[flat, extruded] {
+            let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+                Some(VertexAttributeValues::Float3(positions)) => positions.clone(),
+                _ => vec![],
+            };
+            assert!(!positions.is_empty());
+            assert!(positions.iter().flatten().all(|v| v.is_finite()));
+        }
+    }
+
+    fn orphan_tail(policy: OrphanTailPolicy) -> (AppBuilder, Entity) {
+        let mut app = asset_app();
+        app.add_resource(policy).add_event::<TailRemeshed>();
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mesh = {
+            let mut mesh = make_mesh(&[], vec![]);
+            make_tail_mesh(&mut mesh, &Player::full(8).tail);
+            resources.get_mut::<Assets<Mesh>>().unwrap().add(mesh)
+        };
+        let player = world.spawn((Player::full(8),));
+        let tail = world.spawn((
+            mesh,
+            Tail {
+                player: Some(player),
+            },
+        ));
+        world.despawn(player).unwrap();
+        let mut stage = SystemStage::single(orphan_tail_system.system());
+        stage.initialize(world, resources);
+        stage.run(world, resources);
+        (app, tail)
+    }
+
+    fn index_count(app: &AppBuilder, tail: Entity) -> usize {
+        let mesh = app.app.world.get::<Handle<Mesh>>(tail).unwrap();
+        let meshes = app.app.resources.get::<Assets<Mesh>>().unwrap();
+        match meshes.get(mesh).unwrap().indices() {
+            Some(Indices::U16(indices)) => indices.len(),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn orphan_tails_follow_the_policy() {
+        let (app, tail) = orphan_tail(OrphanTailPolicy::Hide);
+        assert_eq!(index_count(&app, tail), 0);
+        let remeshed = app.app.resources.get::<Events<TailRemeshed>>().unwrap();
+        assert_eq!(remeshed.iter_current_update_events().count(), 1);
+        drop(remeshed);
+
+        let (app, tail) = orphan_tail(OrphanTailPolicy::Freeze);
+        assert!(index_count(&app, tail) > 0);
+
+        let (app, tail) = orphan_tail(OrphanTailPolicy::Despawn);
+        assert!(app.app.world.get::<Tail>(tail).is_err());
+    }
+
+    fn assert_curve(curve: ResponseCurve, middle: impl Fn(f32) -> bool) {
+        assert!(curve.apply(0.).abs() < 1e-6);
+        assert!((curve.apply(1.) - 1.).abs() < 1e-6);
+        let samples: Vec<f32> = (0..=10).map(|i| curve.apply(i as f32 / 10.)).collect();
+        assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(middle(curve.apply(0.5)), "{}", curve.apply(0.5));
+    }
+
+    #[test]
+    fn response_curve_linear() {
+        assert_curve(ResponseCurve::Linear, |t| (t - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn response_curve_log_lifts_the_slow_end() {
+        assert_curve(ResponseCurve::Log, |t| t > 0.6);
+    }
+
+    #[test]
+    fn response_curve_exp_holds_the_slow_end_down() {
+        assert_curve(ResponseCurve::Exp, |t| t < 0.4);
+    }
+
+    #[test]
+    fn response_curve_custom_is_clamped() {
+        assert_curve(ResponseCurve::from_arg("smoothstep").unwrap(), |t| {
+            (t - 0.5).abs() < 1e-6
+        });
+        let overshoot = ResponseCurve::Custom(|t| t * 2. - 0.5);
+        assert_eq!(overshoot.apply(0.), 0.);
+        assert_eq!(overshoot.apply(1.), 1.);
+        assert!(ResponseCurve::from_arg("cubic").is_none());
+    }
+
+    fn float_attribute(mesh: &Mesh, name: &'static str) -> Vec<f32> {
+        match mesh.attribute(name) {
+            Some(VertexAttributeValues::Float(values)) => values.clone(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn node_attributes_reach_the_3d_tail() {
+        let mut nodes = zig_zag(6);
+        nodes[2].velocity = Vec2::zero();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            node.age = i as f32;
+        }
+        let colors: Vec<f32> = (0..nodes.len()).map(|i| i as f32 / 10.).collect();
+        for &extruded in &[false, true] {
+            let mut mesh = make_mesh(&[], vec![]);
+            if extruded {
+                make_tail_mesh_3d(&mut mesh, &nodes, 20.);
+            } else {
+                make_tail_mesh(&mut mesh, &nodes);
+            }
+            set_node_colors(&mut mesh, &colors);
+            set_idle_nodes(&mut mesh, &nodes, 0.5);
+            set_node_decay(&mut mesh, &nodes, 1.);
+
+            let node_of = float_attribute(&mesh, "Vertex_N");
+            let xs = float_attribute(&mesh, "Vertex_X");
+            let decays = float_attribute(&mesh, "Vertex_D");
+            assert_eq!(node_of.len(), vertex_count(&mesh));
+            for ((&n, &x), &d) in node_of.iter().zip(&xs).zip(&decays) {
+                let n = n as usize;
+                let expected = if n == 2 { -1. } else { colors[n] };
+                assert_eq!(x, expected);
+                assert!((d - (1. - (-(n as f32)).exp())).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn tail_shaders_compile() {
+        for &(stage, source) in &[
+            (ShaderStage::Vertex, VERTEX_SHADER),
+            (ShaderStage::Fragment, FRAGMENT_SHADER),
+            (ShaderStage::Vertex, VERTEX_SHADER_3D),
+            (ShaderStage::Fragment, FRAGMENT_SHADER_3D),
+        ] {
+            let shader = Shader::from_glsl(stage, source);
+            assert!(shader.get_spirv(None).is_ok(), "{:?} shader", stage);
+        }
+    }
+
+    #[test]
+    fn stamp_cache_drops_unused_meshes() {
+        let mut app = asset_app();
+        app.add_resource(StampMeshCache::default());
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let (kept, dropped) = {
+            let mut meshes = resources.get_mut::<Assets<Mesh>>().unwrap();
+            (
+                meshes.add(make_mesh(&[], vec![])),
+                meshes.add(make_mesh(&[], vec![])),
+            )
+        };
+        {
+            let mut cache = resources.get_mut::<StampMeshCache>().unwrap();
+            cache.meshes.insert(vec![[1; 6]], kept.clone());
+            cache.meshes.insert(vec![[2; 6]], dropped);
+        }
+        let stamp = world.spawn((kept, Stamp));
+        let mut stage = SystemStage::single(stamp_cache_system.system());
+        stage.initialize(world, resources);
+        stage.run(world, resources);
+        let keys: Vec<Vec<StampNodeKey>> = resources
+            .get::<StampMeshCache>()
+            .unwrap()
+            .meshes
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(keys, vec![vec![[1; 6]]]);
+
+        world.despawn(stamp).unwrap();
+        stage.run(world, resources);
+        assert!(resources.get::<StampMeshCache>().unwrap().meshes.is_empty());
+    }
+
+    fn positions(mesh: &Mesh) -> Vec<Vec2> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => {
+                positions.iter().map(|p| Vec2::new(p[0], p[1])).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn prediction_follows_the_velocity() {
+        let mut app = asset_app();
+        app.add_resource(TrajectoryPrediction {
+            enabled: true,
+            horizon: 0.5,
+        });
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mesh = resources
+            .get_mut::<Assets<Mesh>>()
+            .unwrap()
+            .add(Mesh::new(PrimitiveTopology::LineStrip));
+        let player = world.spawn((
+            Player::full(2),
+            Transform::from_translation(Vec3::new(10., 20., 0.)),
+            Velocity {
+                value: Vec2::new(100., 0.),
+                ..Default::default()
+            },
+        ));
+        let line = world.spawn((
+            mesh.clone(),
+            PredictionLine {
+                player: Some(player),
+            },
+            Visible::default(),
+        ));
+        let mut stage = SystemStage::single(prediction_system.system());
+        stage.initialize(world, resources);
+        let mut run = |world: &mut World, resources: &mut Resources| {
+            stage.run(world, resources);
+            let meshes = resources.get::<Assets<Mesh>>().unwrap();
+            let line_mesh = meshes.get(&mesh).unwrap();
+            assert!(line_mesh.attribute("Vertex_X").is_some());
+            (
+                positions(line_mesh),
+                world.get::<Visible>(line).unwrap().is_visible,
+            )
+        };
+
+        let (points, visible) = run(world, resources);
+        assert!(visible);
+        assert_eq!(points.len(), PREDICTION_SEGMENTS + 1);
+        assert_eq!(points[0], Vec2::new(10., 20.));
+        assert_eq!(points[PREDICTION_SEGMENTS], Vec2::new(60., 20.));
+
+        world.get_mut::<Velocity>(player).unwrap().value = Vec2::new(0., -40.);
+        let (points, _) = run(world, resources);
+        assert_eq!(points[PREDICTION_SEGMENTS], Vec2::new(10., 0.));
+
+        resources.get_mut::<TrajectoryPrediction>().unwrap().enabled = false;
+        let (points, visible) = run(world, resources);
+        assert!(!visible);
+        assert!(points.is_empty());
+    }
+
+    // left of or on every edge of the counter clockwise hull
+    fn inside_hull(hull: &[Vec2], point: Vec2) -> bool {
+        hull.iter()
+            .zip(hull.iter().cycle().skip(1))
+            .all(|(&a, &b)| (b - a).perp_dot(point - a) >= -1e-3)
+    }
+
+    #[test]
+    fn hull_wraps_every_ribbon_vertex() {
+        let tail = debug_player().tail;
+        let vertices = ribbon_vertices(&tail);
+        let hull = convex_hull(&vertices);
+        assert!(hull.len() >= 3);
+        assert!(vertices.iter().all(|&v| inside_hull(&hull, v)));
+
+        let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+        make_hull_mesh(&mut mesh, &hull);
+        let points = positions(&mesh);
+        assert_eq!(points.len(), hull.len() + 1);
+        assert_eq!(points.first(), points.last());
+
+        make_hull_mesh(&mut mesh, &hull[..1]);
+        assert!(positions(&mesh).is_empty());
+        assert!(mesh.attribute("Vertex_X").is_some());
+    }
+
+    // presses T with one main tail per player, the Tail entities come back in order
+    fn stamp_tails(players: Vec<Player>) -> (AppBuilder, Vec<Entity>) {
+        let mut app = asset_app();
+        let mut input = Input::<KeyCode>::default();
+        input.press(KeyCode::T);
+        app.add_resource(input)
+            .add_resource(StampMeshCache::default())
+            .add_resource(TailSmoothing {
+                window: 1,
+                normal_window: 1,
+            });
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mut tails = vec![];
+        for player in players {
+            let material = resources
+                .get_mut::<Assets<MyMaterialWithVertexColorSupport>>()
+                .unwrap()
+                .add(MyMaterialWithVertexColorSupport::default());
+            let player = world.spawn((player,));
+            tails.push(world.spawn((
+                Tail {
+                    player: Some(player),
+                },
+                material,
+                RenderPipelines::default(),
+                Transform::from_translation(Vec3::new(0., 0., 3.)),
+            )));
+        }
+        let mut stage = SystemStage::single(stamp_system.system());
+        stage.initialize(world, resources);
+        stage.run(world, resources);
+        (app, tails)
+    }
+
+    fn stamps(app: &AppBuilder) -> Vec<(Handle<Mesh>, Handle<MyMaterialWithVertexColorSupport>)> {
+        app.app
+            .world
+            .query::<(
+                &Handle<Mesh>,
+                &Handle<MyMaterialWithVertexColorSupport>,
+                &Stamp,
+            )>()
+            .map(|(mesh, material, _)| (mesh.clone(), material.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn identical_stamps_share_a_mesh() {
+        // same positions and widths as the first two, but velocity and gaps change the ribbon
+        let mut turned = Player::full(8);
+        for node in turned.tail.iter_mut() {
+            node.velocity = Vec2::new(0., 1.);
+        }
+        let mut split = Player::full(8);
+        split.tail[3].gap = true;
+        let (app, tails) = stamp_tails(vec![Player::full(8), Player::full(8), turned, split]);
+        let stamps = stamps(&app);
+        assert_eq!(stamps.len(), 4);
+        let mut meshes: Vec<_> = stamps.iter().map(|(mesh, _)| mesh.id).collect();
+        meshes.sort();
+        meshes.dedup();
+        assert_eq!(meshes.len(), 3);
+        assert_eq!(
+            app.app
+                .resources
+                .get::<StampMeshCache>()
+                .unwrap()
+                .meshes
+                .len(),
+            3
+        );
+
+        let tail_materials: Vec<_> = tails
+            .iter()
+            .map(|&tail| {
+                app.app
+                    .world
+                    .get::<Handle<MyMaterialWithVertexColorSupport>>(tail)
+                    .unwrap()
+                    .id
+            })
+            .collect();
+        let mut materials: Vec<_> = stamps.iter().map(|(_, material)| material.id).collect();
+        materials.sort();
+        materials.dedup();
+        assert_eq!(materials.len(), 4);
+        assert!(materials.iter().all(|id| !tail_materials.contains(id)));
+    }
+
+    #[test]
+    fn branches_track_on_their_own() {
+        let mut app = asset_app();
+        let mut input = Input::<KeyCode>::default();
+        input.press(KeyCode::F);
+        let mut timer = Timer::from_seconds(0.01, true);
+        timer.tick(0.02);
+        app.add_resource(input)
+            .add_resource(TailTimer(timer))
+            .add_resource(BranchConfig { angle: 0.5 })
+            .add_resource(TailSmoothing {
+                window: 1,
+                normal_window: 1,
+            });
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let material = resources
+            .get_mut::<Assets<MyMaterialWithVertexColorSupport>>()
+            .unwrap()
+            .add(MyMaterialWithVertexColorSupport::default());
+        let mut player = Player::full(8);
+        let origin = player.tail[0].pos;
+        player.position = origin;
+        let shared = player.tail.clone();
+        let player = world.spawn((player, Transform::from_translation(origin.extend(0.))));
+        world.spawn((
+            Tail {
+                player: Some(player),
+            },
+            material,
+            RenderPipelines::default(),
+            Transform::from_translation(Vec3::new(0., 0., 3.)),
+        ));
+        let mut fork = SystemStage::single(fork_input_system.system());
+        fork.initialize(world, resources);
+        fork.run(world, resources);
+
+        let (branch, mesh, trans) = world
+            .query::<(Entity, &Handle<Mesh>, &Transform, &BranchTail)>()
+            .map(|(entity, mesh, trans, _)| (entity, mesh.clone(), *trans))
+            .next()
+            .unwrap();
+        assert_eq!(trans.translation.z, 3. + BRANCH_Z);
+        {
+            let meshes = resources.get::<Assets<Mesh>>().unwrap();
+            let mesh = meshes.get(&mesh).unwrap();
+            assert!(mesh.attribute("Vertex_X").is_some());
+            assert!(matches!(mesh.indices(), Some(Indices::U16(i)) if !i.is_empty()));
+        }
+
+        let mut track = SystemStage::single(branch_tail_system.system());
+        track.initialize(world, resources);
+        let step = Vec2::new(40., 0.);
+        for i in 1..=3 {
+            let pos = origin + step * i as f32;
+            world.get_mut::<Transform>(player).unwrap().translation = pos.extend(0.);
+            world.get_mut::<Player>(player).unwrap().push_tail_node(pos);
+            track.run(world, resources);
+        }
+
+        let history = &world.get::<BranchTail>(branch).unwrap().history;
+        let player = world.get::<Player>(player).unwrap();
+        // one ribbon up to the fork, two after it
+        assert_eq!(history.tail[3..], shared[..history.tail.len() - 3]);
+        assert_eq!(player.tail[3..], shared[..player.tail.len() - 3]);
+        for i in 0..3 {
+            let moved = step * (3 - i) as f32;
+            assert_eq!(player.tail[i].pos, origin + moved);
+            assert!(history.tail[i].pos.distance(origin + rotate(moved, 0.5)) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn tail_layers_order_stamps_and_branches() {
+        let (app, _) = stamp_tails(vec![Player::full(8)]);
+        let z = app
+            .app
+            .world
+            .query::<(&Transform, &Stamp)>()
+            .map(|(trans, _)| trans.translation.z)
+            .next()
+            .unwrap();
+        assert_eq!(z, 3. + STAMP_Z);
+
+        let offsets = [
+            HULL_Z,
+            0.,
+            -TAIL_DEPTH_SPREAD,
+            GHOST_Z,
+            BRANCH_Z,
+            OUTLINE_Z,
+            STAMP_Z,
+        ];
+        let highest = |z: f32| offsets.iter().fold(f32::MIN, |m, o| m.max(z + o));
+        let lowest = |z: f32| offsets.iter().fold(f32::MAX, |m, o| m.min(z + o));
+        // a sprite at 0 stays in front of a tail layer at -5 and behind one at 5
+        for &(priority, order) in &[(0, 0), (1, 3), (-1, 7)] {
+            assert!(highest(-5. + render_priority_z(priority, order)) < 0.);
+            assert!(lowest(5. + render_priority_z(priority, order)) > 0.);
+        }
+        // everything of a higher priority player draws over a lower one's
+        for order in 0..8 {
+            let below = highest(render_priority_z(0, order));
+            assert!(lowest(render_priority_z(1, 0)) > below);
+        }
+    }
+
+    #[test]
+    fn cursor_maps_to_world_at_a_2x_scale_factor() {
+        // a 1280x720 logical window drawn at 2560x1440 physical pixels
+        let physical = Vec2::new(2560., 1440.);
+        let origin = Vec2::new(30., -20.);
+        assert_eq!(
+            cursor_to_world(Vec2::new(640., 360.), physical, 2., origin),
+            -origin
+        );
+        assert_eq!(
+            cursor_to_world(Vec2::new(1280., 720.), physical, 2., Vec2::zero()),
+            Vec2::new(640., 360.)
+        );
+        assert_eq!(
+            cursor_to_world(Vec2::new(0., 0.), physical, 2., Vec2::zero()),
+            Vec2::new(-640., -360.)
+        );
+        // the same cursor on a 1x screen of the same logical size lands in the same spot
+        assert_eq!(
+            cursor_to_world(
+                Vec2::new(1000., 100.),
+                Vec2::new(1280., 720.),
+                1.,
+                Vec2::zero()
+            ),
+            cursor_to_world(Vec2::new(1000., 100.), physical, 2., Vec2::zero())
+        );
+    }
+
+    #[test]
+    fn finished_replay_matches_the_saved_tail() {
+        for &saved_len in &[5, 40] {
+            let saved = Player::full(saved_len).tail;
+            let mut app = asset_app();
+            let mut timer = Timer::from_seconds(0.01, true);
+            timer.tick(0.02);
+            app.add_resource(TailTimer(timer))
+                .add_resource(ReplaySpeed(1.0));
+            let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+            let mut player = debug_player();
+            let replay = ReplayTail::start(&mut player, &saved);
+            let entity = world.spawn((player, Transform::default(), replay));
+            let mut stage = SystemStage::single(replay_system.system());
+            stage.initialize(world, resources);
+            for _ in 0..saved_len {
+                stage.run(world, resources);
+            }
+            assert!(world.get::<ReplayTail>(entity).is_err());
+            assert_eq!(world.get::<Player>(entity).unwrap().tail, saved);
+        }
+    }
+
+    #[test]
+    fn merged_tails_join_at_the_heads() {
+        let mut a = Player::full(6);
+        a.tail[3].gap = true;
+        let mut b = Player::full(0);
+        b.set_tail_len(4);
+        for i in 0..4 {
+            b.push_tail_node(Vec2::new(-20. - i as f32 * 10., 40.));
+        }
+        let merged = merge_tails(&a, &b);
+        assert_eq!(merged.len(), 10);
+        let bridge = a.tail.len() - 1;
+        assert_eq!(merged[bridge].pos, a.tail[0].pos);
+        assert_eq!(merged[bridge + 1].pos, b.tail[0].pos);
+
+        // a's gap moved with its segment, nothing cuts the bridge
+        let gaps = tail_gaps(&merged);
+        assert_eq!(gaps.iter().filter(|&&gap| gap).count(), 1);
+        assert!(gaps[bridge - 4]);
+        assert!(!gaps[bridge]);
+        let indices = make_tail_indices(merged.len(), &gaps);
+        assert!(indices
+            .chunks(3)
+            .any(|t| t.contains(&(bridge as u16)) && t.contains(&(bridge as u16 + 1))));
+
+        // the bridge points from b's head to a's, like a push would have
+        assert_eq!(merged[bridge].velocity, a.tail[0].pos - b.tail[0].pos);
+        let mut mesh = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut mesh, &merged);
+        assert!(positions(&mesh).iter().all(|p| p.is_finite()));
+    }
+
+    fn straight_tail(len: usize) -> Vec<TailNode> {
+        let mut player = Player::full(0);
+        player.set_tail_len(len);
+        for i in 0..len {
+            player.push_tail_node(Vec2::new(i as f32 * 10., i as f32 * 5.));
+        }
+        player.tail
+    }
+
+    #[test]
+    fn decimate_tail_keeps_only_the_shape() {
+        let straight = straight_tail(20);
+        let decimated = decimate_tail(&straight, 0.5);
+        assert_eq!(decimated.len(), 2);
+        assert_eq!(decimated[0].pos, straight[0].pos);
+        assert_eq!(decimated[1].pos, straight[19].pos);
+        assert_eq!(decimate_tail(&straight, 0.).len(), 20);
+
+        // the corners of a zig zag are further off than the tolerance
+        let corners = zig_zag(5);
+        assert_eq!(decimate_tail(&corners, 0.5).len(), 5);
+
+        // both sides of a gap stay
+        let mut split = straight_tail(20);
+        split[9].gap = true;
+        let kept: Vec<Vec2> = decimate_tail(&split, 0.5).iter().map(|n| n.pos).collect();
+        assert_eq!(
+            kept,
+            vec![split[0].pos, split[9].pos, split[10].pos, split[19].pos]
+        );
+
+        let mut poisoned = straight_tail(20);
+        poisoned[7].pos = Vec2::new(f32::NAN, 0.);
+        decimate_tail(&poisoned, 0.5);
+    }
+
+    #[test]
+    fn smoothing_window_of_one_keeps_the_raw_tail() {
+        let player = debug_player();
+        assert_eq!(player.smoothed_tail(1), player.tail);
+        assert_eq!(player.smoothed_tail(0), player.tail);
+
+        let mut jagged = Player::full(0);
+        jagged.tail = zig_zag(5);
+        let smoothed = jagged.smoothed_tail(3);
+        assert_eq!(smoothed[2].pos, Vec2::new(20., 80. / 3.));
+    }
+
+    #[test]
+    fn normal_window_of_one_builds_the_old_mesh() {
+        let player = debug_player();
+        let mut old = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut old, &player.tail);
+        let smoothing = TailSmoothing {
+            window: 1,
+            normal_window: 1,
+        };
+        let mut smoothed = make_mesh(&[], vec![]);
+        make_tail_mesh(&mut smoothed, &smoothing.smooth(&player));
+        assert_eq!(positions(&smoothed), positions(&old));
+        assert_eq!(
+            float_attribute(&smoothed, "Vertex_X"),
+            float_attribute(&old, "Vertex_X")
+        );
+
+        // a wider window does turn the normals on a zig zag
+        let mut jagged = Player::full(0);
+        jagged.tail = zig_zag(6);
+        for (i, node) in jagged.tail.iter_mut().enumerate() {
+            node.velocity = if i % 2 == 0 {
+                Vec2::new(1., 1.)
+            } else {
+                Vec2::new(1., -1.)
+            };
+        }
+        let wide = TailSmoothing {
+            window: 1,
+            normal_window: 3,
+        };
+        assert_ne!(wide.smooth(&jagged)[2].velocity, jagged.tail[2].velocity);
+    }
+
+    #[test]
+    fn spawning_past_max_players_fails_without_entities() {
+        let mut app = asset_app();
+        let (world, resources) = (&mut app.app.world, &mut app.app.resources);
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        {
+            let mut meshes = resources.get_mut::<Assets<Mesh>>().unwrap();
+            let mut materials = resources
+                .get_mut::<Assets<MyMaterialWithVertexColorSupport>>()
+                .unwrap();
+            let mut count = PlayerCount {
+                players: 1,
+                tails: 1,
+            };
+            let max = MaxPlayers(1);
+            let player = spawn_player(
+                &mut commands,
+                &mut count,
+                &max,
+                Player::full(4),
+                None,
+                &mut meshes,
+                Handle::default(),
+                PlayerColor(Color::WHITE),
+                Vec3::zero(),
+            );
+            assert!(matches!(player, Err(SpawnError::MaxPlayers(1))));
+            let tail = attach_tail(
+                &mut commands,
+                &mut count,
+                &max,
+                Entity::new(0),
+                MeshBundle::default(),
+                MyMaterialWithVertexColorSupport::default(),
+                &mut materials,
+            );
+            assert!(matches!(tail, Err(SpawnError::MaxPlayers(1))));
+            assert_eq!((count.players, count.tails), (1, 1));
+            assert_eq!(meshes.len(), 0);
+            assert_eq!(materials.len(), 0);
+        }
+        commands.apply(world, resources);
+        assert_eq!(world.query::<Entity>().count(), 0);
+    }
+
+    // which players crossed which tails in one tail_collision_system run, with
+    // `b`'s head at `b_head` and `a`'s head on its own tail
+    fn crossings(b_head: Vec2) -> (Vec<(Entity, Entity)>, Entity, Entity) {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Time::default());
+        resources.insert(TailCollision { enabled: true });
+        resources.insert(SpawnGrace::default());
+        resources.insert(Events::<CrossedTailEvent>::default());
+        let a = Player::full(8);
+        let a_head = a.tail[3].pos;
+        let mut b = Player::full(8);
+        for node in &mut b.tail {
+            node.pos += Vec2::new(1000., 1000.);
+        }
+        let mut spawn = |player: Player, head: Vec2| {
+            world.spawn((
+                player,
+                Transform::from_translation(head.extend(0.)),
+                SpawnTime(-10.),
+            ))
+        };
+        let a = spawn(a, a_head);
+        let b = spawn(b, b_head);
+        let mut stage = SystemStage::single(tail_collision_system.system());
+        stage.initialize(&mut world, &mut resources);
+        stage.run(&mut world, &mut resources);
+        let events = resources.get::<Events<CrossedTailEvent>>().unwrap();
+        let crossed = events
+            .iter_current_update_events()
+            .map(|event| (event.crosser, event.owner))
+            .collect();
+        (crossed, a, b)
+    }
+
+    #[test]
+    fn only_other_players_tails_collide() {
+        let (crossed, _, _) = crossings(Vec2::new(-500., -500.));
+        assert!(crossed.is_empty());
+
+        let (crossed, a, b) = crossings(Player::full(8).tail[5].pos);
+        assert_eq!(crossed, vec![(b, a)]);
+    }
+
+    // the tail's node indices grouped by the ribbon piece their triangles join them to
+    fn ribbon_pieces(tail: &[TailNode]) -> Vec<Vec<usize>> {
+        let len = tail.len();
+        let mut piece: Vec<usize> = (0..3 * len - 2).collect();
+        fn root(piece: &mut [usize], mut i: usize) -> usize {
+            while piece[i] != i {
+                i = piece[i];
+            }
+            i
+        }
+        for triangle in make_tail_indices(len, &tail_gaps(tail)).chunks(3) {
+            let a = root(&mut piece, triangle[0] as usize);
+            for &b in &triangle[1..] {
+                let b = root(&mut piece, b as usize);
+                piece[b] = a;
+            }
+        }
+        let mut pieces: Vec<(usize, Vec<usize>)> = vec![];
+        for node in 0..len {
+            let r = root(&mut piece, node);
+            match pieces.iter_mut().find(|(other, _)| *other == r) {
+                Some((_, nodes)) => nodes.push(node),
+                None => pieces.push((r, vec![node])),
+            }
+        }
+        pieces.into_iter().map(|(_, nodes)| nodes).collect()
+    }
+
+    fn pushed_tail(xs: &[f32]) -> Vec<TailNode> {
+        let mut player = Player::full(0);
+        player.set_tail_len(xs.len());
+        for &x in xs {
+            player.push_tail_node(Vec2::new(x, 0.));
+        }
+        player.tail
+    }
+
+    #[test]
+    fn teleports_and_wraps_split_the_ribbon_in_two() {
+        let teleported = pushed_tail(&[0., 10., 20., 620., 630., 640.]);
+        assert_eq!(
+            ribbon_pieces(&teleported),
+            vec![vec![0, 1, 2], vec![3, 4, 5]]
+        );
+
+        // out the right edge and back in from the left
+        let wrapped = pushed_tail(&[600., 620., 635., 650., -650., -635., -620.]);
+        let clipped = clip_tail_to_arena(&wrapped, &Arena::default());
+        let xs: Vec<f32> = clipped.iter().map(|node| node.pos.x).collect();
+        assert_eq!(xs, vec![-620., -635., -640., 640., 635., 620., 600.]);
+        assert_eq!(
+            ribbon_pieces(&clipped),
+            vec![vec![0, 1, 2], vec![3, 4, 5, 6]]
+        );
+    }
+
+    #[test]
+    fn tail_grid_snaps_pushed_nodes() {
+        let moves = [
+            Vec2::new(3., 4.),
+            Vec2::new(13., -6.),
+            Vec2::new(27.5, 14.9),
+        ];
+        let push = |grid: &TailGrid| {
+            let mut player = Player::full(0);
+            player.set_tail_len(moves.len());
+            for &pos in &moves {
+                player.push_tail_node(grid.snap(pos));
+            }
+            centerline(&player.tail)
+        };
+        let snapped = push(&TailGrid { cell_size: 10. });
+        assert_eq!(
+            snapped,
+            vec![Vec2::new(30., 10.), Vec2::new(10., -10.), Vec2::new(0., 0.)]
+        );
+        let free = push(&TailGrid::default());
+        assert_eq!(free, moves.iter().rev().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn fresh_tail_builds_no_triangles() {
+        let mut player = Player::full(0);
+        player.set_tail_len(TAIL_LEN);
+        let mut mesh = make_mesh(&[], vec![]);
+        for _ in 0..2 {
+            make_tail_mesh(&mut mesh, &player.tail);
+            assert!(matches!(mesh.indices(), Some(Indices::U16(i)) if i.is_empty()));
+            // nothing left at the origin to streak from
+            assert!(positions(&mesh).is_empty());
+            player.push_tail_node(Vec2::new(200., 100.));
+        }
+        make_tail_mesh(&mut mesh, &player.tail);
+        assert!(matches!(mesh.indices(), Some(Indices::U16(i)) if !i.is_empty()));
+    }
+}
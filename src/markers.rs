@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::{get_normal, Player};
+
+const MARKER_THICKNESS: f32 = 4.;
+
+pub struct DistanceMarkers {
+    pub enabled: bool,
+    // world units of tail between two ticks
+    pub spacing: f32,
+}
+
+impl Default for DistanceMarkers {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            spacing: 100.,
+        }
+    }
+}
+
+// pooled like the tail sprites, hidden when the tail gets shorter
+pub struct DistanceMarker {
+    player: Entity,
+    index: usize,
+}
+
+pub struct DistanceMarkerMaterial(Handle<ColorMaterial>);
+
+pub fn setup_distance_markers(
+    commands: &mut Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(DistanceMarkerMaterial(
+        materials.add(Color::rgba(1., 1., 1., 0.8).into()),
+    ));
+}
+
+pub fn distance_marker_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut markers: ResMut<DistanceMarkers>,
+) {
+    if keyboard_input.just_pressed(KeyCode::M) {
+        markers.enabled = !markers.enabled;
+    }
+}
+
+pub fn distance_marker_system(
+    commands: &mut Commands,
+    config: Res<DistanceMarkers>,
+    material: Res<DistanceMarkerMaterial>,
+    players: Query<(Entity, &Player)>,
+    mut markers: Query<(&DistanceMarker, &mut Sprite, &mut Transform, &mut Visible)>,
+) {
+    for (player_entity, player) in players.iter() {
+        let count = if config.enabled && config.spacing > 0. {
+            (player.tail_length() / config.spacing) as usize
+        } else {
+            0
+        };
+        let mut pooled = 0;
+        for (marker, mut sprite, mut trans, mut visible) in markers.iter_mut() {
+            if marker.player != player_entity {
+                continue;
+            }
+            pooled += 1;
+            // ticks start one spacing behind the head, the head itself needs none
+            let distance = (marker.index + 1) as f32 * config.spacing;
+            let node = match player.sample_tail(distance) {
+                Some(node) if marker.index < count => node,
+                _ => {
+                    visible.is_visible = false;
+                    continue;
+                }
+            };
+            visible.is_visible = true;
+            // across the ribbon, which runs from the centerline out along the normal
+            let normal = get_normal(node.velocity);
+            sprite.size = Vec2::new(MARKER_THICKNESS, node.width);
+            let center = node.pos + normal * node.width / 2.;
+            trans.translation = center.extend(0.1);
+            trans.rotation =
+                Quat::from_rotation_z(normal.y.atan2(normal.x) - std::f32::consts::FRAC_PI_2);
+        }
+
+        // new markers show up next frame, once the spawn commands are applied
+        for index in pooled..count {
+            commands
+                .spawn(SpriteBundle {
+                    material: material.0.clone(),
+                    sprite: Sprite {
+                        size: Vec2::zero(),
+                        resize_mode: SpriteResizeMode::Manual,
+                    },
+                    ..Default::default()
+                })
+                .with(DistanceMarker {
+                    player: player_entity,
+                    index,
+                });
+        }
+    }
+}
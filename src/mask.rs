@@ -0,0 +1,328 @@
+use std::borrow::Cow;
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        camera::ActiveCameras,
+        pass::{
+            LoadOp, Operations, PassDescriptor, RenderPassColorAttachmentDescriptor,
+            RenderPassDepthStencilAttachmentDescriptor, TextureAttachment,
+        },
+        pipeline::{PipelineDescriptor, RenderPipeline},
+        render_graph::{
+            base::{self, MainPass},
+            AssetRenderResourcesNode, CameraNode, Node, PassNode, RenderGraph, ResourceSlotInfo,
+            ResourceSlots,
+        },
+        renderer::{
+            RenderContext, RenderResourceContext, RenderResourceId, RenderResourceType,
+            RenderResources, TextureId,
+        },
+        shader::{ShaderStage, ShaderStages},
+        texture::{
+            Extent3d, SamplerDescriptor, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsage, SAMPLER_ASSET_INDEX, TEXTURE_ASSET_INDEX,
+        },
+    },
+};
+
+use crate::{
+    add_rainbow_pipeline, make_rounded_rect_mesh, Extruded, Tail, TailPipelines, FRAGMENT_SHADER,
+};
+
+const MASK_CAMERA: &str = "mask_camera";
+const MASK_TEXTURE_NODE: &str = "mask_texture";
+const MASK_PASS: &str = "mask_pass";
+const MASK_MATERIAL_NODE: &str = "tail_mask_material";
+const DEFAULT_MASK_SIZE: f32 = 400.;
+
+const MASK_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 0x27c4_9e3b_d150_6af8);
+
+const MASK_VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+}
+"#;
+
+const MASK_FRAGMENT_SHADER: &str = r#"
+#version 450
+layout(location = 0) out vec4 o_Target;
+void main() {
+    o_Target = vec4(1.0);
+}
+"#;
+
+// goes in front of the tail fragment shader's main, the mask covers the whole window
+const MASK_BINDINGS: &str = r#"
+layout(set = 3, binding = 0) uniform texture2D TailMaskMaterial_mask;
+layout(set = 3, binding = 1) uniform sampler TailMaskMaterial_mask_sampler;
+
+float tail_mask()
+{
+    vec2 size = vec2(textureSize(sampler2D(TailMaskMaterial_mask, TailMaskMaterial_mask_sampler), 0));
+    return texture(
+        sampler2D(TailMaskMaterial_mask, TailMaskMaterial_mask_sampler),
+        gl_FragCoord.xy / size
+    ).r;
+}
+
+void main() {"#;
+
+// bevy 0.4's depth buffer has no stencil bits, so the mask mesh is drawn into a
+// texture of its own first and the flat tail discards wherever that stayed empty;
+// it's in window pixels around the center, the camera moving doesn't move it
+pub struct TailMask {
+    pub enabled: bool,
+    // None draws a rounded square in the middle of the window
+    pub mesh: Option<Handle<Mesh>>,
+    // should match the window, resizing it doesn't resize the mask
+    pub width: u32,
+    pub height: u32,
+    material: Option<Handle<TailMaskMaterial>>,
+}
+
+impl Default for TailMask {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mesh: None,
+            width: 1280,
+            height: 720,
+            material: None,
+        }
+    }
+}
+
+#[derive(RenderResources, TypeUuid)]
+#[uuid = "b3f1d2c4-8e97-4a16-9d5b-7c0e6f1a2b38"]
+pub struct TailMaskMaterial {
+    mask: Handle<Texture>,
+}
+
+#[derive(Default)]
+pub struct MaskPass;
+
+struct MaskTextureNode {
+    color: TextureId,
+    depth: TextureId,
+}
+
+impl Node for MaskTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[
+            ResourceSlotInfo {
+                name: Cow::Borrowed("color"),
+                resource_type: RenderResourceType::Texture,
+            },
+            ResourceSlotInfo {
+                name: Cow::Borrowed("depth"),
+                resource_type: RenderResourceType::Texture,
+            },
+        ];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        _resources: &Resources,
+        _render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        output.set(0, RenderResourceId::Texture(self.color));
+        output.set(1, RenderResourceId::Texture(self.depth));
+    }
+}
+
+pub fn setup_mask(
+    commands: &mut Commands,
+    mut mask: ResMut<TailMask>,
+    msaa: Res<Msaa>,
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+    mut active_cameras: ResMut<ActiveCameras>,
+    mut render_graph: ResMut<RenderGraph>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut mask_materials: ResMut<Assets<TailMaskMaterial>>,
+) {
+    if !mask.enabled {
+        return;
+    }
+    let size = Extent3d::new(mask.width, mask.height, 1);
+    let color = render_resource_context.create_texture(TextureDescriptor {
+        size,
+        sample_count: msaa.samples,
+        format: TextureFormat::default(),
+        usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+        ..Default::default()
+    });
+    let depth = render_resource_context.create_texture(TextureDescriptor {
+        size,
+        sample_count: msaa.samples,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Depth32Float,
+        usage: TextureUsage::OUTPUT_ATTACHMENT,
+        ..Default::default()
+    });
+    let sampler = render_resource_context.create_sampler(&SamplerDescriptor::default());
+    let texture: Handle<Texture> = MASK_TEXTURE_HANDLE.typed();
+    render_resource_context.set_asset_resource(
+        &texture,
+        RenderResourceId::Texture(color),
+        TEXTURE_ASSET_INDEX,
+    );
+    render_resource_context.set_asset_resource(
+        &texture,
+        RenderResourceId::Sampler(sampler),
+        SAMPLER_ASSET_INDEX,
+    );
+
+    let mut pass = PassNode::<&MaskPass>::new(PassDescriptor {
+        color_attachments: vec![RenderPassColorAttachmentDescriptor {
+            attachment: TextureAttachment::Input("color_attachment".to_string()),
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color::NONE),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: msaa.samples,
+    });
+    pass.add_camera(MASK_CAMERA);
+    active_cameras.add(MASK_CAMERA);
+
+    render_graph.add_system_node(MASK_CAMERA, CameraNode::new(MASK_CAMERA));
+    render_graph.add_system_node(
+        MASK_MATERIAL_NODE,
+        AssetRenderResourcesNode::<TailMaskMaterial>::new(true),
+    );
+    render_graph.add_node(MASK_TEXTURE_NODE, MaskTextureNode { color, depth });
+    render_graph.add_node(MASK_PASS, pass);
+    render_graph
+        .add_slot_edge(MASK_TEXTURE_NODE, "color", MASK_PASS, "color_attachment")
+        .unwrap();
+    render_graph
+        .add_slot_edge(MASK_TEXTURE_NODE, "depth", MASK_PASS, "depth")
+        .unwrap();
+    render_graph.add_node_edge(MASK_CAMERA, MASK_PASS).unwrap();
+    // the mask has to be there before the main pass samples it
+    render_graph
+        .add_node_edge(MASK_PASS, base::node::MAIN_PASS)
+        .unwrap();
+    render_graph
+        .add_node_edge(MASK_MATERIAL_NODE, base::node::MAIN_PASS)
+        .unwrap();
+
+    let mut camera = Camera2dBundle::default();
+    camera.camera.name = Some(MASK_CAMERA.to_string());
+    commands.spawn(camera);
+
+    let mesh = mask
+        .mesh
+        .get_or_insert_with(|| {
+            meshes.add(make_rounded_rect_mesh(
+                DEFAULT_MASK_SIZE,
+                DEFAULT_MASK_SIZE / 5.,
+                8,
+            ))
+        })
+        .clone();
+    let pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, MASK_VERTEX_SHADER)),
+        fragment: Some(shaders.add(Shader::from_glsl(
+            ShaderStage::Fragment,
+            MASK_FRAGMENT_SHADER,
+        ))),
+    }));
+    // only the mask camera should see it
+    commands.spawn(MeshBundle {
+        mesh,
+        render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]),
+        ..Default::default()
+    });
+    let mask_entity = commands.current_entity().unwrap();
+    commands.remove_one::<MainPass>(mask_entity);
+    commands.insert_one(mask_entity, MaskPass);
+
+    mask.material = Some(mask_materials.add(TailMaskMaterial { mask: texture }));
+}
+
+#[derive(Default)]
+pub struct MaskedPipeline(Option<Handle<PipelineDescriptor>>);
+
+// the flat tail and its outline switch to the masked pipeline, again after every
+// RenderDimension swap; the vertex stage is kept, the fragment stage is the built-in
+pub fn tail_mask_system(
+    commands: &mut Commands,
+    mask: Res<TailMask>,
+    tail_pipelines: Res<TailPipelines>,
+    mut masked: Local<MaskedPipeline>,
+    mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut tails: Query<
+        (
+            Entity,
+            &mut RenderPipelines,
+            Option<&Handle<TailMaskMaterial>>,
+        ),
+        (With<Tail>, Without<Extruded>),
+    >,
+) {
+    let mask_material = match &mask.material {
+        Some(material) if mask.enabled => material,
+        _ => return,
+    };
+    if masked.0.is_none() {
+        let vertex = match pipelines.get(&tail_pipelines.flat) {
+            Some(flat) => flat.shader_stages.vertex.clone(),
+            None => return,
+        };
+        let fragment_src = FRAGMENT_SHADER
+            .replacen("void main() {", MASK_BINDINGS, 1)
+            .replacen(
+                "    o_Target = vec4(",
+                "    if (tail_mask() < 0.5) {\n        discard;\n    }\n    o_Target = vec4(",
+                1,
+            );
+        let stages = ShaderStages {
+            vertex,
+            fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, &fragment_src))),
+        };
+        masked.0 = Some(add_rainbow_pipeline(stages, &mut pipelines));
+    }
+    let masked = masked.0.as_ref().unwrap();
+    for (entity, mut render_pipelines, material) in tails.iter_mut() {
+        if render_pipelines
+            .pipelines
+            .iter()
+            .all(|p| p.pipeline == *masked)
+        {
+            continue;
+        }
+        *render_pipelines =
+            RenderPipelines::from_pipelines(vec![RenderPipeline::new(masked.clone())]);
+        if material.is_none() {
+            commands.insert_one(entity, mask_material.clone());
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+
+use crate::{palette, Arena, Player, MAX_TAIL_LEN};
+
+const DOT_SIZE: f32 = 4.;
+
+// `--minimap`
+pub struct Minimap {
+    pub enabled: bool,
+    pub size: f32,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            size: 160.,
+        }
+    }
+}
+
+pub struct MinimapRoot;
+
+// one per drawn node across every player, more are spawned when the tails outgrow them
+pub struct MinimapDot {
+    index: usize,
+}
+
+// one per player, grown like the dots
+pub struct MinimapHead {
+    index: usize,
+}
+
+fn dot_style(size: f32) -> Style {
+    Style {
+        position_type: PositionType::Absolute,
+        size: Size::new(Val::Px(size), Val::Px(size)),
+        ..Default::default()
+    }
+}
+
+fn place_dot(style: &mut Style, minimap: &Minimap, arena: &Arena, pos: Vec2, size: f32) {
+    let extent = arena.max - arena.min;
+    let scaled = ((pos - arena.min) / extent)
+        .max(Vec2::zero())
+        .min(Vec2::one())
+        * minimap.size;
+    style.position.left = Val::Px(scaled.x - size / 2.);
+    style.position.bottom = Val::Px(scaled.y - size / 2.);
+}
+
+// every dot and head gets its own material, the color is per node
+fn spawn_dots(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    root: Entity,
+    dots: std::ops::Range<usize>,
+    heads: std::ops::Range<usize>,
+) {
+    let mut children = vec![];
+    for index in dots {
+        commands
+            .spawn(NodeBundle {
+                style: dot_style(DOT_SIZE),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with(MinimapDot { index });
+        children.push(commands.current_entity().unwrap());
+    }
+    for index in heads {
+        commands
+            .spawn(NodeBundle {
+                style: dot_style(DOT_SIZE * 2.),
+                material: materials.add(Color::NONE.into()),
+                ..Default::default()
+            })
+            .with(MinimapHead { index });
+        children.push(commands.current_entity().unwrap());
+    }
+    commands.push_children(root, &children);
+}
+
+pub fn setup_minimap(
+    commands: &mut Commands,
+    minimap: Res<Minimap>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !minimap.enabled {
+        return;
+    }
+    let background = materials.add(Color::rgba(0., 0., 0., 0.5).into());
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(minimap.size), Val::Px(minimap.size)),
+                ..Default::default()
+            },
+            material: background,
+            ..Default::default()
+        })
+        .with(MinimapRoot);
+    let root = commands.current_entity().unwrap();
+    // enough for one player at the longest tail, a second one grows the pool
+    spawn_dots(commands, &mut materials, root, 0..MAX_TAIL_LEN, 0..1);
+}
+
+pub fn minimap_system(
+    commands: &mut Commands,
+    minimap: Res<Minimap>,
+    arena: Res<Arena>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<(&Player, &Transform)>,
+    roots: Query<Entity, With<MinimapRoot>>,
+    mut dots: Query<(&MinimapDot, &mut Style, &Handle<ColorMaterial>), Without<MinimapHead>>,
+    mut heads: Query<(&MinimapHead, &mut Style, &Handle<ColorMaterial>), Without<MinimapDot>>,
+) {
+    if !minimap.enabled {
+        return;
+    }
+    let mut points = vec![];
+    let mut head_points = vec![];
+    for (player, trans) in players.iter() {
+        let centerline = player.centerline();
+        for (i, pos) in centerline.iter().enumerate() {
+            let along = i as f32 / centerline.len() as f32;
+            let rgb = palette::rainbow_color(along);
+            points.push((*pos, Color::rgba(rgb.x, rgb.y, rgb.z, 1. - along)));
+        }
+        head_points.push(Vec2::new(trans.translation.x, trans.translation.y));
+    }
+
+    let dot_count = dots.iter_mut().count();
+    let head_count = heads.iter_mut().count();
+    if points.len() > dot_count || head_points.len() > head_count {
+        if let Some(root) = roots.iter().next() {
+            let dots = dot_count..points.len().max(dot_count);
+            let heads = head_count..head_points.len().max(head_count);
+            spawn_dots(commands, &mut materials, root, dots, heads);
+        }
+    }
+
+    for (dot, mut style, material) in dots.iter_mut() {
+        let color = match points.get(dot.index) {
+            Some(&(pos, color)) => {
+                place_dot(&mut style, &minimap, &arena, pos, DOT_SIZE);
+                color
+            }
+            None => Color::NONE,
+        };
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+    }
+    for (head, mut style, material) in heads.iter_mut() {
+        let color = match head_points.get(head.index) {
+            Some(&pos) => {
+                place_dot(&mut style, &minimap, &arena, pos, DOT_SIZE * 2.);
+                Color::WHITE
+            }
+            None => Color::NONE,
+        };
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color;
+        }
+    }
+}
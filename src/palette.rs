@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+
+pub const PALETTE_STOPS: usize = 6;
+
+pub type PaletteStops = [Vec4; PALETTE_STOPS];
+
+// rainbow from: https://github.com/wsmind/js-pride
+const RAINBOW: [[f32; 3]; PALETTE_STOPS] = [
+    [1.0, 0.0, 0.0],
+    [1.0, 0.5, 0.0],
+    [1.0, 1.0, 0.0],
+    [0.0, 0.5, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.5, 0.0, 0.5],
+];
+
+const VIRIDIS: [[f32; 3]; PALETTE_STOPS] = [
+    [0.267, 0.005, 0.329],
+    [0.229, 0.322, 0.546],
+    [0.128, 0.567, 0.551],
+    [0.369, 0.789, 0.383],
+    [0.741, 0.873, 0.150],
+    [0.993, 0.906, 0.144],
+];
+
+const SUNSET: [[f32; 3]; PALETTE_STOPS] = [
+    [0.98, 0.84, 0.38],
+    [0.98, 0.60, 0.30],
+    [0.93, 0.40, 0.33],
+    [0.78, 0.25, 0.45],
+    [0.50, 0.20, 0.50],
+    [0.25, 0.15, 0.40],
+];
+
+const GRAYSCALE: [[f32; 3]; PALETTE_STOPS] = [
+    [0.0, 0.0, 0.0],
+    [0.2, 0.2, 0.2],
+    [0.4, 0.4, 0.4],
+    [0.6, 0.6, 0.6],
+    [0.8, 0.8, 0.8],
+    [1.0, 1.0, 1.0],
+];
+
+pub const PRESETS: [(&str, [[f32; 3]; PALETTE_STOPS]); 4] = [
+    ("rainbow", RAINBOW),
+    ("viridis", VIRIDIS),
+    ("sunset", SUNSET),
+    ("grayscale", GRAYSCALE),
+];
+
+pub fn make_stops(colors: &[[f32; 3]; PALETTE_STOPS]) -> PaletteStops {
+    let mut stops = [Vec4::zero(); PALETTE_STOPS];
+    for (stop, [r, g, b]) in stops.iter_mut().zip(colors.iter()) {
+        *stop = Vec4::new(*r, *g, *b, 1.0);
+    }
+    stops
+}
+
+pub fn rainbow_stops() -> PaletteStops {
+    make_stops(&RAINBOW)
+}
+
+// cpu port of `rainbow` in FRAGMENT_SHADER
+pub fn palette_color(stops: &PaletteStops, x: f32) -> Vec3 {
+    let level = (x * PALETTE_STOPS as f32)
+        .floor()
+        .clamp(0.0, (PALETTE_STOPS - 1) as f32);
+    stops[level as usize].truncate()
+}
+
+pub fn rainbow_color(x: f32) -> Vec3 {
+    palette_color(&rainbow_stops(), x)
+}
+
+pub const PALETTE_PNG_WIDTH: u32 = 256;
+
+pub fn export_palette_png(path: &Path) -> image::ImageResult<()> {
+    let mut strip = image::RgbaImage::new(PALETTE_PNG_WIDTH, 1);
+    for (i, pixel) in strip.pixels_mut().enumerate() {
+        // sample pixel centers so x never reaches 1.0
+        let x = (i as f32 + 0.5) / PALETTE_PNG_WIDTH as f32;
+        let color = rainbow_color(x);
+        *pixel = image::Rgba([
+            (color.x * 255.).round() as u8,
+            (color.y * 255.).round() as u8,
+            (color.z * 255.).round() as u8,
+            255,
+        ]);
+    }
+    strip.save(path)
+}
+
+// averages `stops` vertical bands of the image, then spreads them over the palette stops
+pub fn palette_from_image(path: &Path, stops: usize) -> image::ImageResult<PaletteStops> {
+    let img = image::open(path)?.into_rgba8();
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Ok(rainbow_stops());
+    }
+    let stops = stops.max(1);
+    let samples: Vec<Vec4> = (0..stops)
+        .map(|band| {
+            let x0 = band as u32 * width / stops as u32;
+            let x1 = ((band as u32 + 1) * width / stops as u32)
+                .max(x0 + 1)
+                .min(width);
+            let mut sum = Vec4::zero();
+            for x in x0..x1 {
+                for y in 0..height {
+                    let [r, g, b, _] = img.get_pixel(x, y).0;
+                    sum += Vec4::new(r as f32, g as f32, b as f32, 255.) / 255.;
+                }
+            }
+            sum / ((x1 - x0) * height) as f32
+        })
+        .collect();
+    let mut palette = [Vec4::zero(); PALETTE_STOPS];
+    for (i, stop) in palette.iter_mut().enumerate() {
+        let t = i as f32 / (PALETTE_STOPS - 1) as f32 * (stops - 1) as f32;
+        let lo = t.floor() as usize;
+        let hi = (lo + 1).min(stops - 1);
+        *stop = samples[lo].lerp(samples[hi], t - lo as f32);
+    }
+    Ok(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_palette_runs_red_to_violet() {
+        let path = std::env::temp_dir().join("bevy_rainbow_test_palette.png");
+        export_palette_png(&path).unwrap();
+        let strip = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(strip.dimensions(), (PALETTE_PNG_WIDTH, 1));
+        assert_eq!(*strip.get_pixel(0, 0), image::Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            *strip.get_pixel(PALETTE_PNG_WIDTH - 1, 0),
+            image::Rgba([128, 0, 128, 255])
+        );
+    }
+}
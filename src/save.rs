@@ -0,0 +1,90 @@
+use std::{fmt, fs, io, path::Path};
+
+use bevy::math::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::TailNode;
+
+// bump when SavedState changes shape, older files are refused instead of misread
+pub const STATE_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MovementMode {
+    Mouse,
+    Target(Vec2),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedState {
+    pub version: u32,
+    pub position: Vec2,
+    pub tail: Vec<TailNode>,
+    pub tail_len: usize,
+    pub palette: Vec<Vec4>,
+    pub movement: MovementMode,
+}
+
+// read before the rest so a version mismatch isn't reported as a parse error
+#[derive(Deserialize)]
+struct StateVersion {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Ron(ron::Error),
+    Version(u32),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "io error: {}", err),
+            SaveError::Ron(err) => write!(f, "ron error: {}", err),
+            SaveError::Version(version) => write!(
+                f,
+                "unsupported state version {}, expected {}",
+                version, STATE_VERSION
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Ron(err)
+    }
+}
+
+pub fn save_tail(nodes: &[TailNode], path: &Path) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(&nodes, ron::ser::PrettyConfig::default())?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn load_tail(path: &Path) -> Result<Vec<TailNode>, SaveError> {
+    let text = fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&text)?)
+}
+
+pub fn save_state(state: &SavedState, path: &Path) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(state, ron::ser::PrettyConfig::default())?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn load_state(path: &Path) -> Result<SavedState, SaveError> {
+    let text = fs::read_to_string(path)?;
+    let StateVersion { version } = ron::de::from_str(&text)?;
+    if version != STATE_VERSION {
+        return Err(SaveError::Version(version));
+    }
+    Ok(ron::de::from_str(&text)?)
+}
@@ -0,0 +1,74 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::capture::{CaptureFrame, CaptureReadback};
+
+// the still is the capture texture read back, at the capture's size, so
+// `--screenshots` turns the capture on too
+pub struct Screenshot {
+    pub enabled: bool,
+    pub key: KeyCode,
+    // created on the first shot if it's missing
+    pub directory: PathBuf,
+}
+
+impl Default for Screenshot {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key: KeyCode::F12,
+            directory: PathBuf::from("screenshots"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ScreenshotState {
+    frame_reader: EventReader<CaptureFrame>,
+    // the key was pressed, the next read back frame is ours
+    waiting: bool,
+}
+
+pub fn screenshot_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    screenshot: Res<Screenshot>,
+    mut state: Local<ScreenshotState>,
+    mut readback: ResMut<CaptureReadback>,
+    frames: Res<Events<CaptureFrame>>,
+) {
+    if !screenshot.enabled {
+        return;
+    }
+    let state = &mut *state;
+    if keyboard_input.just_pressed(screenshot.key) {
+        readback.request();
+        state.waiting = true;
+    }
+    let frame = match state.frame_reader.latest(&frames) {
+        Some(frame) if state.waiting => frame,
+        _ => return,
+    };
+    state.waiting = false;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis());
+    let path = screenshot
+        .directory
+        .join(format!("screenshot-{}.png", millis));
+    if let Err(err) = std::fs::create_dir_all(&screenshot.directory) {
+        println!(
+            "failed to create {}: {}",
+            screenshot.directory.display(),
+            err
+        );
+        return;
+    }
+    match frame.0.save(&path) {
+        Ok(()) => info!("screenshot written to {}", path.display()),
+        Err(err) => println!("failed to write screenshot to {}: {}", path.display(), err),
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{Player, Velocity};
+
+const RING_TEXTURE_SIZE: u32 = 64;
+
+pub struct Shockwave {
+    pub enabled: bool,
+    // speed lost per second that counts as slamming on the brakes
+    pub deceleration: f32,
+    pub lifetime: f32,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub color: Color,
+    // the oldest ring goes first when a new one would pass this
+    pub max_rings: usize,
+}
+
+impl Default for Shockwave {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            deceleration: 20000.,
+            lifetime: 0.4,
+            start_size: 40.,
+            end_size: 300.,
+            color: Color::WHITE,
+            max_rings: 8,
+        }
+    }
+}
+
+pub struct ShockwaveRing {
+    age: f32,
+}
+
+pub struct ShockwaveTexture(Handle<Texture>);
+
+fn make_ring() -> Texture {
+    let size = RING_TEXTURE_SIZE;
+    let center = (size as f32 - 1.) / 2.;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let d = Vec2::new(x as f32 - center, y as f32 - center).length() / (size as f32 / 2.);
+            // thin soft band just inside the edge
+            let alpha = (1. - (d - 0.85).abs() / 0.1).clamp(0., 1.);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.) as u8]);
+        }
+    }
+    Texture::new(
+        Extent3d::new(size, size, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+pub fn setup_shockwave(commands: &mut Commands, mut textures: ResMut<Assets<Texture>>) {
+    commands.insert_resource(ShockwaveTexture(textures.add(make_ring())));
+}
+
+pub fn shockwave_spawn_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    shockwave: Res<Shockwave>,
+    texture: Res<ShockwaveTexture>,
+    mut last_speeds: Local<HashMap<Entity, f32>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<(Entity, &Transform, &Velocity), With<Player>>,
+    rings: Query<(Entity, &ShockwaveRing)>,
+) {
+    let dt = time.delta_seconds();
+    if dt <= 0. {
+        return;
+    }
+    let mut count = rings.iter().count();
+    for (entity, trans, velocity) in players.iter() {
+        let speed = velocity.value.length();
+        let last_speed = last_speeds.insert(entity, speed).unwrap_or(speed);
+        if !shockwave.enabled || (last_speed - speed) / dt < shockwave.deceleration {
+            continue;
+        }
+        if count >= shockwave.max_rings {
+            let oldest = rings
+                .iter()
+                .max_by(|(_, a), (_, b)| a.age.partial_cmp(&b.age).unwrap());
+            if let Some((oldest, _)) = oldest {
+                commands.despawn(oldest);
+                count -= 1;
+            }
+        }
+        count += 1;
+        commands
+            .spawn(SpriteBundle {
+                material: materials.add(ColorMaterial {
+                    color: shockwave.color,
+                    texture: Some(texture.0.clone()),
+                }),
+                sprite: Sprite {
+                    size: Vec2::splat(shockwave.start_size),
+                    resize_mode: SpriteResizeMode::Manual,
+                },
+                transform: Transform::from_translation(trans.translation + Vec3::unit_z() * 0.1),
+                ..Default::default()
+            })
+            .with(ShockwaveRing { age: 0. });
+    }
+}
+
+pub fn shockwave_ring_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    shockwave: Res<Shockwave>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rings: Query<(
+        Entity,
+        &mut ShockwaveRing,
+        &mut Sprite,
+        &Handle<ColorMaterial>,
+    )>,
+) {
+    for (entity, mut ring, mut sprite, material) in rings.iter_mut() {
+        ring.age += time.delta_seconds();
+        let t = ring.age / shockwave.lifetime.max(f32::EPSILON);
+        if t >= 1. {
+            commands.despawn(entity);
+            continue;
+        }
+        // fast at first, easing out as it spreads
+        let spread = 1. - (1. - t) * (1. - t);
+        sprite.size = Vec2::splat(
+            shockwave.start_size + (shockwave.end_size - shockwave.start_size) * spread,
+        );
+        if let Some(material) = materials.get_mut(material) {
+            material.color = Color::rgba(
+                shockwave.color.r(),
+                shockwave.color.g(),
+                shockwave.color.b(),
+                shockwave.color.a() * (1. - t),
+            );
+        }
+    }
+}
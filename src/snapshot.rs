@@ -0,0 +1,128 @@
+use std::{fs, path::Path};
+
+use bevy::{
+    math::Vec3,
+    render::mesh::{Indices, Mesh, VertexAttributeValues},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{make_mesh, make_tail_mesh, save::SaveError, Player};
+
+const SNAPSHOT_TAIL_LEN: usize = 128;
+// world units a vertex may move before the check fails
+const SNAPSHOT_TOLERANCE: f32 = 1e-3;
+
+// positions and indices of a built ribbon, ron on disk like the saved tails
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TailSnapshot {
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+impl TailSnapshot {
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => positions.clone(),
+            _ => vec![],
+        };
+        let indices = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|&i| i as u32).collect(),
+            Some(Indices::U32(indices)) => indices.clone(),
+            None => vec![],
+        };
+        Self { positions, indices }
+    }
+
+    // largest distance between matching vertices, different topology never matches
+    pub fn diff(&self, other: &TailSnapshot) -> f32 {
+        if self.positions.len() != other.positions.len() || self.indices != other.indices {
+            return f32::INFINITY;
+        }
+        self.positions
+            .iter()
+            .zip(other.positions.iter())
+            .map(|(a, b)| Vec3::from(*a).distance(Vec3::from(*b)))
+            .fold(0., f32::max)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SaveError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&text)?)
+    }
+}
+
+// the tail the fixture is taken of, a wavy Player::full as a flat ribbon
+fn build_snapshot() -> TailSnapshot {
+    let player = Player::full(SNAPSHOT_TAIL_LEN);
+    let mut mesh = make_mesh(&[], vec![]);
+    make_tail_mesh(&mut mesh, &player.tail);
+    TailSnapshot::from_mesh(&mesh)
+}
+
+// `--snapshot-tail <path>` builds the snapshot tail and compares it to the fixture,
+// writing the fixture instead when there isn't one yet; false when the check failed
+pub fn run_snapshot_check(path: &Path) -> bool {
+    let snapshot = build_snapshot();
+    if !path.exists() {
+        return match snapshot.save(path) {
+            Ok(()) => {
+                println!("tail snapshot written to {}", path.display());
+                true
+            }
+            Err(err) => {
+                println!("failed to write tail snapshot: {}", err);
+                false
+            }
+        };
+    }
+    let fixture = match TailSnapshot::load(path) {
+        Ok(fixture) => fixture,
+        Err(err) => {
+            println!("failed to read tail snapshot: {}", err);
+            return false;
+        }
+    };
+    let diff = snapshot.diff(&fixture);
+    if diff <= SNAPSHOT_TOLERANCE {
+        println!("tail snapshot matches, max vertex distance {}", diff);
+        true
+    } else {
+        println!(
+            "tail snapshot differs from {}, max vertex distance {}",
+            path.display(),
+            diff
+        );
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // regenerate with `--snapshot-tail fixtures/tail_snapshot.ron` after deleting it
+    fn fixture_path() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/tail_snapshot.ron")
+    }
+
+    #[test]
+    fn tail_mesh_matches_fixture() {
+        let fixture = TailSnapshot::load(&fixture_path()).expect("fixture should load");
+        let diff = build_snapshot().diff(&fixture);
+        assert!(diff <= SNAPSHOT_TOLERANCE, "max vertex distance {}", diff);
+    }
+
+    #[test]
+    fn diff_rejects_other_topology() {
+        let fixture = TailSnapshot::load(&fixture_path()).expect("fixture should load");
+        let mut snapshot = build_snapshot();
+        snapshot.indices.pop();
+        assert_eq!(snapshot.diff(&fixture), f32::INFINITY);
+    }
+}
@@ -0,0 +1,130 @@
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{palette, Player, Tail};
+
+const SPRITE_TEXTURE_SIZE: u32 = 32;
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum TailStyle {
+    #[default]
+    Ribbon,
+    // one soft circle per node instead of the ribbon mesh
+    Sprites,
+}
+
+// pooled, hidden instead of despawned when the tail gets shorter
+pub struct TailSprite {
+    player: Entity,
+    index: usize,
+}
+
+pub struct TailSpriteTexture(Handle<Texture>);
+
+fn make_soft_circle() -> Texture {
+    let size = SPRITE_TEXTURE_SIZE;
+    let center = (size as f32 - 1.) / 2.;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let d = Vec2::new(x as f32 - center, y as f32 - center).length() / (size as f32 / 2.);
+            let alpha = (1. - d).clamp(0., 1.);
+            data.extend_from_slice(&[255, 255, 255, (alpha * alpha * 255.) as u8]);
+        }
+    }
+    Texture::new(
+        Extent3d::new(size, size, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+pub fn setup_tail_sprites(commands: &mut Commands, mut textures: ResMut<Assets<Texture>>) {
+    commands.insert_resource(TailSpriteTexture(textures.add(make_soft_circle())));
+}
+
+pub fn tail_style_input_system(keyboard_input: Res<Input<KeyCode>>, mut style: ResMut<TailStyle>) {
+    if keyboard_input.just_pressed(KeyCode::B) {
+        *style = match *style {
+            TailStyle::Ribbon => TailStyle::Sprites,
+            TailStyle::Sprites => TailStyle::Ribbon,
+        };
+    }
+}
+
+pub fn tail_sprite_system(
+    commands: &mut Commands,
+    style: Res<TailStyle>,
+    texture: Res<TailSpriteTexture>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<(Entity, &Player)>,
+    mut sprites: Query<(
+        &TailSprite,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visible,
+        &Handle<ColorMaterial>,
+    )>,
+    mut ribbons: Query<&mut Visible, (With<Tail>, Without<TailSprite>)>,
+) {
+    let use_sprites = *style == TailStyle::Sprites;
+    for mut visible in ribbons.iter_mut() {
+        visible.is_visible = !use_sprites;
+    }
+
+    for (player_entity, player) in players.iter() {
+        let centerline = player.centerline();
+        let mut pooled = 0;
+        for (tail_sprite, mut sprite, mut trans, mut visible, material) in sprites.iter_mut() {
+            if tail_sprite.player != player_entity {
+                continue;
+            }
+            pooled += 1;
+            let node = match player.tail.get(tail_sprite.index) {
+                Some(node) if use_sprites => node,
+                _ => {
+                    visible.is_visible = false;
+                    continue;
+                }
+            };
+            visible.is_visible = true;
+            sprite.size = Vec2::splat(node.width);
+            trans.translation.x = centerline[tail_sprite.index].x;
+            trans.translation.y = centerline[tail_sprite.index].y;
+            // newest node on top
+            trans.translation.z = -0.5 - tail_sprite.index as f32 * 0.001;
+            if let Some(material) = materials.get_mut(material) {
+                let along = tail_sprite.index as f32 / centerline.len() as f32;
+                let rgb = palette::rainbow_color(along);
+                material.color = Color::rgba(rgb.x, rgb.y, rgb.z, 1. - along);
+            }
+        }
+
+        if !use_sprites {
+            continue;
+        }
+        // new sprites show up next frame, once the spawn commands are applied
+        for index in pooled..centerline.len() {
+            commands
+                .spawn(SpriteBundle {
+                    material: materials.add(ColorMaterial {
+                        color: Color::NONE,
+                        texture: Some(texture.0.clone()),
+                    }),
+                    sprite: Sprite {
+                        size: Vec2::zero(),
+                        resize_mode: SpriteResizeMode::Manual,
+                    },
+                    transform: Transform::from_translation(Vec3::new(0., 0., -0.5)),
+                    ..Default::default()
+                })
+                .with(TailSprite {
+                    player: player_entity,
+                    index,
+                });
+        }
+    }
+}
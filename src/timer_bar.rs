@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+use crate::TailTimer;
+
+const BAR_WIDTH: f32 = 160.;
+const BAR_HEIGHT: f32 = 6.;
+
+// F3, fills up while TailTimer runs and empties when tail_gen_system pushes a node
+#[derive(Default)]
+pub struct TimerBar {
+    pub enabled: bool,
+}
+
+pub struct TimerBarBackground;
+
+pub struct TimerBarFill;
+
+pub fn setup_timer_bar(commands: &mut Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let background = materials.add(Color::rgba(0., 0., 0., 0.5).into());
+    let fill = materials.add(Color::WHITE.into());
+    let hidden = Visible {
+        is_visible: false,
+        ..Default::default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.),
+                    top: Val::Px(10.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                ..Default::default()
+            },
+            material: background,
+            visible: hidden.clone(),
+            ..Default::default()
+        })
+        .with(TimerBarBackground)
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    material: fill,
+                    visible: hidden,
+                    ..Default::default()
+                })
+                .with(TimerBarFill);
+        });
+}
+
+pub fn timer_bar_input_system(keyboard_input: Res<Input<KeyCode>>, mut bar: ResMut<TimerBar>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        bar.enabled = !bar.enabled;
+        info!("tail timer bar: {}", bar.enabled);
+    }
+}
+
+pub fn timer_bar_system(
+    bar: Res<TimerBar>,
+    tail_timer: Res<TailTimer>,
+    mut backgrounds: Query<&mut Visible, (With<TimerBarBackground>, Without<TimerBarFill>)>,
+    mut fills: Query<(&mut Style, &mut Visible), With<TimerBarFill>>,
+) {
+    // ui children aren't hidden along with their parent, so both are toggled
+    for mut visible in backgrounds.iter_mut() {
+        visible.is_visible = bar.enabled;
+    }
+    for (mut style, mut visible) in fills.iter_mut() {
+        visible.is_visible = bar.enabled;
+        if bar.enabled {
+            style.size.width = Val::Percent(tail_timer.0.percent() * 100.);
+        }
+    }
+}